@@ -0,0 +1,224 @@
+use crate::{Crc32, DataFile, Game, Sha1};
+use std::collections::{HashMap, HashSet};
+
+/// The differences between two versions of a DAT
+#[derive(Clone, Debug, Default)]
+pub struct Diff<'a> {
+    /// Games which only appear in the new DAT
+    pub added: Vec<&'a Game>,
+    /// Games which only appear in the old DAT
+    pub removed: Vec<&'a Game>,
+    /// Games whose contents are unchanged but which have a new name
+    pub renamed: Vec<Change<'a>>,
+    /// Games whose name is unchanged but whose contents have been re-dumped
+    pub redumped: Vec<Change<'a>>,
+}
+
+/// A game as it appears in the old and new versions of a DAT
+#[derive(Clone, Copy, Debug)]
+pub struct Change<'a> {
+    pub old: &'a Game,
+    pub new: &'a Game,
+}
+
+impl<'a> Diff<'a> {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty()
+            && self.removed.is_empty()
+            && self.renamed.is_empty()
+            && self.redumped.is_empty()
+    }
+}
+
+/// Compare two versions of a DAT. Games are matched by name first; games whose name disappeared
+/// are then matched by the hashes of their contents to detect renames.
+pub fn diff<'a>(old: &'a DataFile, new: &'a DataFile) -> Diff<'a> {
+    let old_names = by_name(old);
+    let new_names = by_name(new);
+    let mut diff = Diff::default();
+
+    let mut renamed_to = HashMap::new();
+    for game in new.games.iter() {
+        let key = content_key(game);
+        if !old_names.contains_key(game.name.as_str()) && !key.is_empty() {
+            renamed_to.entry(key).or_insert(game);
+        }
+    }
+
+    let mut renamed_names = HashSet::new();
+    for old_game in old.games.iter() {
+        match new_names.get(old_game.name.as_str()) {
+            Some(new_game) => {
+                if content_key(old_game) != content_key(new_game) {
+                    diff.redumped.push(Change {
+                        old: old_game,
+                        new: new_game,
+                    });
+                }
+            }
+            None => match renamed_to.remove(&content_key(old_game)) {
+                Some(new_game) => {
+                    renamed_names.insert(new_game.name.as_str());
+                    diff.renamed.push(Change {
+                        old: old_game,
+                        new: new_game,
+                    });
+                }
+                None => diff.removed.push(old_game),
+            },
+        }
+    }
+
+    diff.added = new
+        .games
+        .iter()
+        .filter(|game| !old_names.contains_key(game.name.as_str()))
+        .filter(|game| !renamed_names.contains(game.name.as_str()))
+        .collect();
+
+    diff
+}
+
+fn by_name(datafile: &DataFile) -> HashMap<&str, &Game> {
+    datafile
+        .games
+        .iter()
+        .map(|game| (game.name.as_str(), game))
+        .collect()
+}
+
+/// The hash used to compare a ROM between DATs
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+enum ContentHash {
+    Sha1(Sha1),
+    Crc(Crc32),
+}
+
+/// Identifies the contents of a game, independent of its name. ROMs without a SHA-1 fall back to
+/// their CRC, and ROMs with neither are left out so that undumped games never match each other.
+fn content_key(game: &Game) -> Vec<(usize, ContentHash)> {
+    let mut key = game
+        .roms
+        .iter()
+        .filter_map(|rom| {
+            let hash = rom
+                .sha1
+                .map(ContentHash::Sha1)
+                .or(rom.crc.map(ContentHash::Crc))?;
+            Some((rom.size, hash))
+        })
+        .collect::<Vec<_>>();
+
+    key.sort_unstable();
+    key
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{ClrMamePro, Header, Rom};
+
+    #[test]
+    fn detects_added_games() {
+        let old = datafile(&[("A", "01")]);
+        let new = datafile(&[("A", "01"), ("B", "02")]);
+        let diff = diff(&old, &new);
+
+        assert_eq!(names(&diff.added), vec!["B"]);
+        assert!(diff.removed.is_empty());
+    }
+
+    #[test]
+    fn detects_removed_games() {
+        let old = datafile(&[("A", "01"), ("B", "02")]);
+        let new = datafile(&[("A", "01")]);
+        let diff = diff(&old, &new);
+
+        assert_eq!(names(&diff.removed), vec!["B"]);
+        assert!(diff.added.is_empty());
+    }
+
+    #[test]
+    fn detects_renamed_games() {
+        let old = datafile(&[("A", "01")]);
+        let new = datafile(&[("A (USA)", "01")]);
+        let diff = diff(&old, &new);
+
+        assert_eq!(diff.renamed.len(), 1);
+        assert_eq!(diff.renamed[0].old.name, "A");
+        assert_eq!(diff.renamed[0].new.name, "A (USA)");
+        assert!(diff.added.is_empty());
+        assert!(diff.removed.is_empty());
+    }
+
+    #[test]
+    fn detects_redumped_games() {
+        let old = datafile(&[("A", "01")]);
+        let new = datafile(&[("A", "02")]);
+        let diff = diff(&old, &new);
+
+        assert_eq!(diff.redumped.len(), 1);
+        assert_eq!(diff.redumped[0].new.roms[0].sha1, hash("02"));
+    }
+
+    #[test]
+    fn ignores_hash_case() {
+        let old = datafile(&[("A", "ab")]);
+        let new = datafile(&[("A", "AB")]);
+
+        assert!(diff(&old, &new).is_empty());
+    }
+
+    #[test]
+    fn ignores_games_without_hashes() {
+        let old = datafile(&[("A", "")]);
+        let new = datafile(&[("B", "")]);
+        let diff = diff(&old, &new);
+
+        assert!(diff.renamed.is_empty());
+        assert_eq!(names(&diff.added), vec!["B"]);
+        assert_eq!(names(&diff.removed), vec!["A"]);
+    }
+
+    fn names<'a>(games: &[&'a Game]) -> Vec<&'a str> {
+        games.iter().map(|game| game.name.as_str()).collect()
+    }
+
+    /// Tests use short hashes for readability, so pad them out to a full SHA-1
+    fn hash(s: &str) -> Option<Sha1> {
+        (!s.is_empty()).then(|| format!("{s:0>40}").parse().unwrap())
+    }
+
+    fn datafile(games: &[(&str, &str)]) -> DataFile {
+        let games = games
+            .iter()
+            .map(|(name, sha1)| Game {
+                name: name.to_string(),
+                description: name.to_string(),
+                roms: vec![Rom {
+                    name: format!("{name}.bin"),
+                    size: 1,
+                    sha1: hash(sha1),
+                    ..Rom::default()
+                }],
+            })
+            .collect();
+
+        DataFile {
+            header: Header {
+                id: 1,
+                name: "Test".to_string(),
+                description: String::new(),
+                version: String::new(),
+                author: String::new(),
+                homepage: String::new(),
+                url: String::new(),
+                subset: None,
+                clrmamepro: ClrMamePro {
+                    force_no_dump: None,
+                },
+            },
+            games,
+        }
+    }
+}
@@ -0,0 +1,133 @@
+use serde::{Deserialize, Serialize};
+use std::error;
+use std::fmt::{self, Display, Formatter};
+use std::str::FromStr;
+
+/// A hash which couldn't be parsed from a string
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ParseHashError {
+    kind: &'static str,
+    value: String,
+    digits: usize,
+}
+
+impl Display for ParseHashError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "invalid {} '{}', expected {} hex digits",
+            self.kind, self.value, self.digits
+        )
+    }
+}
+
+impl error::Error for ParseHashError {}
+
+macro_rules! hash_type {
+    ($(#[$meta:meta])* $name:ident, $len:literal, $kind:literal) => {
+        $(#[$meta])*
+        #[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize)]
+        pub struct $name([u8; $len]);
+
+        impl $name {
+            pub fn from_bytes(bytes: [u8; $len]) -> Self {
+                Self(bytes)
+            }
+
+            pub fn as_bytes(&self) -> &[u8; $len] {
+                &self.0
+            }
+        }
+
+        impl FromStr for $name {
+            type Err = ParseHashError;
+
+            /// Parse a hash from hex digits, in either case
+            fn from_str(s: &str) -> Result<Self, Self::Err> {
+                let err = || ParseHashError {
+                    kind: $kind,
+                    value: s.to_owned(),
+                    digits: $len * 2,
+                };
+
+                if s.len() != $len * 2 || !s.bytes().all(|b| b.is_ascii_hexdigit()) {
+                    return Err(err());
+                }
+
+                let mut bytes = [0; $len];
+                for (i, byte) in bytes.iter_mut().enumerate() {
+                    *byte = u8::from_str_radix(&s[i * 2..i * 2 + 2], 16).map_err(|_| err())?;
+                }
+
+                Ok(Self(bytes))
+            }
+        }
+
+        /// Hashes are always displayed as lowercase hex
+        impl Display for $name {
+            fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+                for byte in self.0 {
+                    write!(f, "{byte:02x}")?;
+                }
+
+                Ok(())
+            }
+        }
+    };
+}
+
+hash_type!(Crc32, 4, "CRC");
+hash_type!(Md5, 16, "MD5");
+hash_type!(Sha1, 20, "SHA-1");
+hash_type!(Sha256, 32, "SHA-256");
+
+impl From<u32> for Crc32 {
+    fn from(value: u32) -> Self {
+        Self(value.to_be_bytes())
+    }
+}
+
+impl From<Crc32> for u32 {
+    fn from(value: Crc32) -> Self {
+        u32::from_be_bytes(value.0)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parses_either_case() {
+        let lower = "0123abcd".parse::<Crc32>().unwrap();
+        let upper = "0123ABCD".parse::<Crc32>().unwrap();
+        assert_eq!(lower, upper);
+    }
+
+    #[test]
+    fn displays_lowercase() {
+        let sha1 = "C3499C2729730A7F807EFB8676A92DCB6F8A3F8F"
+            .parse::<Sha1>()
+            .unwrap();
+        assert_eq!(sha1.to_string(), "c3499c2729730a7f807efb8676a92dcb6f8a3f8f");
+    }
+
+    #[test]
+    fn converts_crc_from_integer() {
+        assert_eq!(Crc32::from(0x0123abcd).to_string(), "0123abcd");
+    }
+
+    #[test]
+    fn rejects_wrong_length() {
+        let err = "148323542".parse::<Crc32>().unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "invalid CRC '148323542', expected 8 hex digits"
+        );
+    }
+
+    #[test]
+    fn rejects_non_hex_digits() {
+        assert!("0123abcg".parse::<Crc32>().is_err());
+    }
+}
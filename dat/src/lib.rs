@@ -1,6 +1,18 @@
-use serde::Deserialize;
+mod diff;
+mod hash;
+mod position;
+mod read;
+mod validate;
+mod write;
+
+pub use diff::{diff, Change, Diff};
+pub use hash::{Crc32, Md5, ParseHashError, Sha1, Sha256};
+pub use position::Position;
+pub use read::Games;
+use serde::{Deserialize, Serialize};
 use std::error;
 use std::fmt::{self, Display, Formatter};
+pub use validate::{validate, Issue};
 
 // Derived from https://datomatic.no-intro.org/stuff/schema_nointro_datfile_v3.xsd
 
@@ -8,24 +20,51 @@ use std::fmt::{self, Display, Formatter};
 pub struct Error {
     message: String,
     path: String,
+    position: Option<Position>,
 }
 
 impl Display for Error {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
-        write!(f, "{} - {}", self.message, self.path)
+        write!(f, "{} - {}", self.message, self.path)?;
+
+        if let Some(position) = self.position {
+            write!(f, " at {position}")?;
+        }
+
+        Ok(())
     }
 }
 
 impl error::Error for Error {}
 
-#[derive(Clone, Debug, Deserialize)]
+impl Error {
+    fn new(message: impl ToString, path: &str) -> Self {
+        Self {
+            message: message.to_string(),
+            path: path.to_owned(),
+            position: None,
+        }
+    }
+
+    fn at(mut self, position: Position) -> Self {
+        self.position = Some(position);
+        self
+    }
+
+    /// Where in the DAT the error was found, if known
+    pub fn position(&self) -> Option<Position> {
+        self.position
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct DataFile {
     pub header: Header,
     #[serde(rename = "game")]
     pub games: Vec<Game>,
 }
 
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
 pub struct Header {
     pub id: i32,
     pub name: String,
@@ -38,7 +77,7 @@ pub struct Header {
     pub clrmamepro: ClrMamePro,
 }
 
-#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq)]
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
 pub enum ForceNoDump {
     #[serde(rename = "obsolete")]
     Obsolete,
@@ -48,45 +87,45 @@ pub enum ForceNoDump {
     Ignore,
 }
 
-#[derive(Clone, Debug, Deserialize, Eq, PartialEq)]
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
 pub struct ClrMamePro {
     #[serde(rename = "forcenodump")]
     pub force_no_dump: Option<ForceNoDump>,
 }
 
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
 pub struct Rom {
     pub name: String,
     pub size: usize,
-    pub crc: String,
-    pub md5: String,
-    pub sha1: String,
-    pub sha256: Option<String>,
+    /// Hashes are missing for ROMs which haven't been dumped
+    pub crc: Option<Crc32>,
+    pub md5: Option<Md5>,
+    pub sha1: Option<Sha1>,
+    pub sha256: Option<Sha256>,
     pub status: Option<String>,
     pub serial: Option<String>,
     pub header: Option<String>,
 }
 
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct Game {
     pub name: String,
     pub description: String,
-    pub rom: Rom,
+    #[serde(rename = "rom")]
+    pub roms: Vec<Rom>,
 }
 
-impl DataFile {
-    pub fn from_file(s: &str) -> Result<Self, Error> {
-        let bytes = s.as_bytes();
-        let mut de = serde_xml_rs::Deserializer::new_from_reader(bytes);
-        let mut data_file: DataFile =
-            serde_path_to_error::deserialize(&mut de).map_err(|err| Error {
-                path: err.path().to_string(),
-                message: err.to_string(),
-            })?;
-
-        data_file.games.shrink_to_fit();
+impl Rom {
+    pub fn is_status(&self, status: &str) -> bool {
+        self.status.as_deref() == Some(status)
+    }
+}
 
-        Ok(data_file)
+impl Game {
+    /// Whether every ROM in this game is known not to have been dumped. Such games can't be
+    /// collected, so are left out of completeness counts.
+    pub fn is_nodump(&self) -> bool {
+        !self.roms.is_empty() && self.roms.iter().all(|rom| rom.is_status("nodump"))
     }
 }
 
@@ -166,28 +205,34 @@ mod test {
     fn parses_rom_size() {
         let dat = v3_sample();
         let game = dat.games.first().unwrap();
-        assert_eq!(game.rom.size, 5432);
+        assert_eq!(game.roms[0].size, 5432);
     }
 
     #[test]
     fn parses_rom_crc() {
         let dat = v3_sample();
         let game = dat.games.first().unwrap();
-        assert_eq!(game.rom.crc, "148323542");
+        assert_eq!(game.roms[0].crc, "08d73cd6".parse().ok());
     }
 
     #[test]
     fn parses_rom_md5() {
         let dat = v3_sample();
         let game = dat.games.first().unwrap();
-        assert_eq!(game.rom.md5, "1a79a4d60de6718e8e5b326e338ae533");
+        assert_eq!(
+            game.roms[0].md5,
+            "1a79a4d60de6718e8e5b326e338ae533".parse().ok()
+        );
     }
 
     #[test]
     fn parses_rom_sha1() {
         let dat = v3_sample();
         let game = dat.games.first().unwrap();
-        assert_eq!(game.rom.sha1, "c3499c2729730a7f807efb8676a92dcb6f8a3f8f");
+        assert_eq!(
+            game.roms[0].sha1,
+            "c3499c2729730a7f807efb8676a92dcb6f8a3f8f".parse().ok()
+        );
     }
 
     #[test]
@@ -195,11 +240,23 @@ mod test {
         let dat = v3_sample();
         let game = dat.games.first().unwrap();
         assert_eq!(
-            game.rom.sha256,
-            Some("50d858e0985ecc7f60418aaf0cc5ab587f42c2570a884095a9e8ccacd0f6545c".to_owned())
+            game.roms[0].sha256,
+            "50d858e0985ecc7f60418aaf0cc5ab587f42c2570a884095a9e8ccacd0f6545c"
+                .parse()
+                .ok()
         );
     }
 
+    #[test]
+    fn detects_nodump_games() {
+        let mut dat = v3_sample();
+        let game = dat.games.first_mut().unwrap();
+        assert!(!game.is_nodump());
+
+        game.roms[0].status = Some("nodump".to_owned());
+        assert!(game.is_nodump());
+    }
+
     fn v3_sample() -> DataFile {
         let txt = read_to_string("samples/v3.dat").unwrap();
         DataFile::from_file(&txt).unwrap()
@@ -0,0 +1,97 @@
+use std::fmt::{self, Display, Formatter};
+use std::io::{self, BufRead, Read};
+
+/// A location in a DAT file. Lines and columns both start from 1, and columns count characters
+/// rather than bytes.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Position {
+    pub line: u64,
+    pub column: u64,
+}
+
+impl Default for Position {
+    fn default() -> Self {
+        Self { line: 1, column: 1 }
+    }
+}
+
+impl Display for Position {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.line, self.column)
+    }
+}
+
+/// Wraps a reader to keep track of how far into the file the parser has read. Along with the
+/// current position, the position of the most recent `<` is kept so that problems can be
+/// reported at the start of the element that caused them.
+pub(crate) struct Tracked<R> {
+    inner: R,
+    current: Position,
+    tag: Position,
+}
+
+impl<R> Tracked<R> {
+    pub fn new(inner: R) -> Self {
+        Self {
+            inner,
+            current: Position::default(),
+            tag: Position::default(),
+        }
+    }
+
+    /// The position just past everything the parser has consumed
+    pub fn current(&self) -> Position {
+        self.current
+    }
+
+    /// The position of the start of the most recently consumed tag
+    pub fn tag(&self) -> Position {
+        self.tag
+    }
+}
+
+impl<R: BufRead> Read for Tracked<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let available = self.fill_buf()?;
+        let len = available.len().min(buf.len());
+        buf[..len].copy_from_slice(&available[..len]);
+        self.consume(len);
+
+        Ok(len)
+    }
+}
+
+impl<R: BufRead> BufRead for Tracked<R> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        self.inner.fill_buf()
+    }
+
+    fn consume(&mut self, amt: usize) {
+        // The bytes being consumed are still buffered, so this doesn't perform any I/O
+        if let Ok(buf) = self.inner.fill_buf() {
+            for &byte in &buf[..amt.min(buf.len())] {
+                track(&mut self.current, &mut self.tag, byte);
+            }
+        }
+
+        self.inner.consume(amt);
+    }
+}
+
+fn track(current: &mut Position, tag: &mut Position, byte: u8) {
+    match byte {
+        b'\n' => {
+            current.line += 1;
+            current.column = 1;
+        }
+        // UTF-8 continuation bytes are part of the previous character
+        byte if byte & 0xc0 == 0x80 => {}
+        byte => {
+            if byte == b'<' {
+                *tag = *current;
+            }
+
+            current.column += 1;
+        }
+    }
+}
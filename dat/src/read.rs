@@ -0,0 +1,604 @@
+use crate::position::Tracked;
+use crate::{
+    ClrMamePro, DataFile, Error, ForceNoDump, Game, Header, ParseHashError, Position, Rom,
+};
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader;
+use std::io::BufRead;
+use std::str::FromStr;
+
+/// Reads the games in a DAT one at a time, so that the whole DAT never needs to be held in
+/// memory at once. The header is read up front, and so must appear before any games.
+pub struct Games<R> {
+    reader: Reader<Tracked<R>>,
+    buf: Vec<u8>,
+    inner_buf: Vec<u8>,
+    header: Header,
+    index: usize,
+    position: Position,
+    roms: RomState,
+    done: bool,
+}
+
+/// Where each ROM of the game being read starts, and when reading leniently, the hashes which
+/// couldn't be parsed
+#[derive(Default)]
+struct RomState {
+    positions: Vec<Position>,
+    invalid_hashes: Vec<Error>,
+    lenient: bool,
+}
+
+impl RomState {
+    fn clear(&mut self) {
+        self.positions.clear();
+        self.invalid_hashes.clear();
+    }
+}
+
+impl<R: BufRead> Games<R> {
+    pub fn new(reader: R) -> Result<Self, Error> {
+        let mut reader = Reader::from_reader(Tracked::new(reader));
+        reader.config_mut().trim_text(true);
+
+        let mut games = Self {
+            reader,
+            buf: Vec::new(),
+            inner_buf: Vec::new(),
+            header: Header::default(),
+            index: 0,
+            position: Position::default(),
+            roms: RomState::default(),
+            done: false,
+        };
+
+        games.header = games.read_header().map_err(|err| games.locate(err))?;
+        Ok(games)
+    }
+
+    pub fn header(&self) -> &Header {
+        &self.header
+    }
+
+    pub fn into_header(self) -> Header {
+        self.header
+    }
+
+    /// Where the most recently read game starts
+    pub fn position(&self) -> Position {
+        self.position
+    }
+
+    /// Where each ROM of the most recently read game starts
+    pub(crate) fn rom_positions(&self) -> &[Position] {
+        &self.roms.positions
+    }
+
+    /// Leave malformed hashes out of ROMs instead of failing, so that every one can be reported
+    pub(crate) fn lenient_hashes(mut self) -> Self {
+        self.roms.lenient = true;
+        self
+    }
+
+    /// The hashes of the most recently read game which couldn't be parsed, when reading leniently
+    pub(crate) fn take_invalid_hashes(&mut self) -> Vec<Error> {
+        std::mem::take(&mut self.roms.invalid_hashes)
+    }
+
+    /// Errors which aren't XML syntax errors come from the values in a tag, so point to the
+    /// start of the last tag that was read
+    fn locate(&self, err: Error) -> Error {
+        match err.position {
+            Some(_) => err,
+            None => err.at(self.reader.get_ref().tag()),
+        }
+    }
+
+    fn read_header(&mut self) -> Result<Header, Error> {
+        loop {
+            self.buf.clear();
+            let event = self
+                .reader
+                .read_event_into(&mut self.buf)
+                .map_err(|err| syntax_error(&self.reader, err, "datafile"))?;
+
+            match event {
+                Event::Start(e) if e.local_name().as_ref() == b"datafile" => {}
+                Event::Start(e) if e.local_name().as_ref() == b"header" => {
+                    return read_header_fields(&mut self.reader, &mut self.inner_buf);
+                }
+                Event::Start(e) | Event::Empty(e) => {
+                    let name = String::from_utf8_lossy(e.local_name().as_ref()).into_owned();
+                    let message = format!("expected a header, but found <{name}>");
+                    return Err(Error::new(message, "datafile"));
+                }
+                Event::Eof => return Err(Error::new("missing header", "datafile")),
+                _ => {}
+            }
+        }
+    }
+
+    fn read_game(&mut self) -> Result<Option<Game>, Error> {
+        loop {
+            self.buf.clear();
+            let event = self
+                .reader
+                .read_event_into(&mut self.buf)
+                .map_err(|err| syntax_error(&self.reader, err, &game_path(self.index)))?;
+
+            match event {
+                Event::Start(e) if is_game(&e) => {
+                    let path = game_path(self.index);
+                    self.position = self.reader.get_ref().tag();
+                    self.roms.clear();
+
+                    let mut game = game_from_attributes(&e, &path)?;
+                    read_game_children(
+                        &mut self.reader,
+                        &mut self.inner_buf,
+                        &mut game,
+                        &mut self.roms,
+                        &path,
+                    )?;
+                    self.index += 1;
+                    return Ok(Some(game));
+                }
+                Event::Empty(e) if is_game(&e) => {
+                    self.position = self.reader.get_ref().tag();
+                    self.roms.clear();
+
+                    let game = game_from_attributes(&e, &game_path(self.index))?;
+                    self.index += 1;
+                    return Ok(Some(game));
+                }
+                Event::Start(e) => {
+                    let end = e.to_end().into_owned();
+                    self.inner_buf.clear();
+                    self.reader
+                        .read_to_end_into(end.name(), &mut self.inner_buf)
+                        .map_err(|err| syntax_error(&self.reader, err, &game_path(self.index)))?;
+                }
+                Event::End(e) if e.local_name().as_ref() == b"datafile" => return Ok(None),
+                Event::Eof => return Ok(None),
+                _ => {}
+            }
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for Games<R> {
+    type Item = Result<Game, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+
+        let result = self.read_game().map_err(|err| self.locate(err));
+        self.done = !matches!(result, Ok(Some(_)));
+
+        result.transpose()
+    }
+}
+
+impl DataFile {
+    /// Parse a DAT from any buffered reader. Prefer this over `from_file` for large DATs, which
+    /// don't need to be read into a string first.
+    pub fn from_reader<R: BufRead>(reader: R) -> Result<Self, Error> {
+        let mut games = Games::new(reader)?;
+        let mut collected = games.by_ref().collect::<Result<Vec<_>, _>>()?;
+        collected.shrink_to_fit();
+
+        Ok(Self {
+            header: games.into_header(),
+            games: collected,
+        })
+    }
+
+    pub fn from_file(s: &str) -> Result<Self, Error> {
+        Self::from_reader(s.as_bytes())
+    }
+}
+
+fn syntax_error<R>(reader: &Reader<Tracked<R>>, err: impl ToString, path: &str) -> Error {
+    Error::new(err, path).at(reader.get_ref().current())
+}
+
+fn game_path(index: usize) -> String {
+    format!("game[{index}]")
+}
+
+/// MAME DATs call games "machines"
+fn is_game(e: &BytesStart) -> bool {
+    matches!(e.local_name().as_ref(), b"game" | b"machine")
+}
+
+fn read_header_fields<R: BufRead>(
+    reader: &mut Reader<Tracked<R>>,
+    buf: &mut Vec<u8>,
+) -> Result<Header, Error> {
+    let mut header = Header::default();
+
+    loop {
+        buf.clear();
+        let event = reader
+            .read_event_into(buf)
+            .map_err(|err| syntax_error(reader, err, "header"))?;
+
+        match event {
+            Event::Start(e) => {
+                let name = String::from_utf8_lossy(e.local_name().as_ref()).into_owned();
+                let path = format!("header.{name}");
+                let start = reader.get_ref().tag();
+
+                if name == "clrmamepro" {
+                    header.clrmamepro = clrmamepro_from_attributes(&e, &path)?;
+                }
+
+                let end = e.to_end().into_owned();
+                let mut text_buf = Vec::new();
+                let text = read_text(reader, &mut text_buf, end.name().as_ref(), &path)?;
+
+                match name.as_str() {
+                    "id" => {
+                        header.id = text.parse().map_err(|_| {
+                            Error::new(format!("invalid id '{text}'"), &path).at(start)
+                        })?
+                    }
+                    "name" => header.name = text,
+                    "description" => header.description = text,
+                    "version" => header.version = text,
+                    "author" => header.author = text,
+                    "homepage" => header.homepage = text,
+                    "url" => header.url = text,
+                    "subset" => header.subset = Some(text),
+                    _ => {}
+                }
+            }
+            Event::Empty(e) if e.local_name().as_ref() == b"clrmamepro" => {
+                header.clrmamepro = clrmamepro_from_attributes(&e, "header.clrmamepro")?;
+            }
+            Event::End(e) if e.local_name().as_ref() == b"header" => return Ok(header),
+            Event::Eof => return Err(Error::new("unexpected end of file", "header")),
+            _ => {}
+        }
+    }
+}
+
+fn read_game_children<R: BufRead>(
+    reader: &mut Reader<Tracked<R>>,
+    buf: &mut Vec<u8>,
+    game: &mut Game,
+    roms: &mut RomState,
+    path: &str,
+) -> Result<(), Error> {
+    loop {
+        buf.clear();
+        let event = reader
+            .read_event_into(buf)
+            .map_err(|err| syntax_error(reader, err, path))?;
+
+        match event {
+            Event::Empty(e) if e.local_name().as_ref() == b"rom" => {
+                read_rom(&e, reader.get_ref().tag(), game, roms, path)?;
+            }
+            Event::Start(e) => {
+                let name = e.local_name().as_ref().to_vec();
+                let child_path = format!("{path}.{}", String::from_utf8_lossy(&name));
+
+                if name == b"rom" {
+                    read_rom(&e, reader.get_ref().tag(), game, roms, path)?;
+                }
+
+                let end = e.to_end().into_owned();
+                let mut text_buf = Vec::new();
+                let text = read_text(reader, &mut text_buf, end.name().as_ref(), &child_path)?;
+
+                if name == b"description" {
+                    game.description = text;
+                }
+            }
+            Event::End(e) if is_game_end(e.local_name().as_ref()) => return Ok(()),
+            Event::Eof => return Err(Error::new("unexpected end of file", path)),
+            _ => {}
+        }
+    }
+}
+
+/// ROMs may be written as an empty element, or with a start and end tag
+fn read_rom(
+    e: &BytesStart,
+    position: Position,
+    game: &mut Game,
+    roms: &mut RomState,
+    path: &str,
+) -> Result<(), Error> {
+    let rom_path = format!("{path}.rom[{}]", game.roms.len());
+    let mut invalid_hashes = roms.lenient.then(Vec::new);
+    let rom = rom_from_attributes(e, &rom_path, invalid_hashes.as_mut())?;
+
+    roms.positions.push(position);
+    roms.invalid_hashes.extend(
+        invalid_hashes
+            .into_iter()
+            .flatten()
+            .map(|err| err.at(position)),
+    );
+    game.roms.push(rom);
+
+    Ok(())
+}
+
+fn is_game_end(name: &[u8]) -> bool {
+    matches!(name, b"game" | b"machine")
+}
+
+/// Read the text content of an element, up to and including its end tag. Any nested elements are
+/// skipped.
+fn read_text<R: BufRead>(
+    reader: &mut Reader<Tracked<R>>,
+    buf: &mut Vec<u8>,
+    end: &[u8],
+    path: &str,
+) -> Result<String, Error> {
+    let mut text = String::new();
+    let mut depth = 0;
+
+    loop {
+        buf.clear();
+        let event = reader
+            .read_event_into(buf)
+            .map_err(|err| syntax_error(reader, err, path))?;
+
+        match event {
+            Event::Text(e) if depth == 0 => {
+                let unescaped = e
+                    .unescape()
+                    .map_err(|err| syntax_error(reader, err, path))?;
+                text.push_str(&unescaped);
+            }
+            Event::CData(e) if depth == 0 => {
+                text.push_str(&String::from_utf8_lossy(&e));
+            }
+            Event::Start(_) => depth += 1,
+            Event::End(e) if depth == 0 && e.name().as_ref() == end => return Ok(text),
+            Event::End(_) => depth -= 1,
+            Event::Eof => return Err(Error::new("unexpected end of file", path)),
+            _ => {}
+        }
+    }
+}
+
+fn game_from_attributes(e: &BytesStart, path: &str) -> Result<Game, Error> {
+    let mut name = None;
+
+    for (key, value) in attributes(e, path)? {
+        if key == "name" {
+            name = Some(value);
+        }
+    }
+
+    let name = name.ok_or_else(|| Error::new("missing attribute 'name'", path))?;
+
+    Ok(Game {
+        name,
+        description: String::new(),
+        roms: Vec::new(),
+    })
+}
+
+/// Malformed hashes fail the ROM, unless `invalid_hashes` is given to collect them in
+fn rom_from_attributes(
+    e: &BytesStart,
+    path: &str,
+    mut invalid_hashes: Option<&mut Vec<Error>>,
+) -> Result<Rom, Error> {
+    let mut rom = Rom::default();
+    let mut has_name = false;
+
+    for (key, value) in attributes(e, path)? {
+        match key.as_str() {
+            "name" => {
+                rom.name = value;
+                has_name = true;
+            }
+            "size" => {
+                rom.size = value.parse().map_err(|_| {
+                    Error::new(format!("invalid size '{value}'"), &format!("{path}.size"))
+                })?
+            }
+            "crc" => rom.crc = parse_hash(&value, path, "crc", invalid_hashes.as_deref_mut())?,
+            "md5" => rom.md5 = parse_hash(&value, path, "md5", invalid_hashes.as_deref_mut())?,
+            "sha1" => rom.sha1 = parse_hash(&value, path, "sha1", invalid_hashes.as_deref_mut())?,
+            "sha256" => {
+                rom.sha256 = parse_hash(&value, path, "sha256", invalid_hashes.as_deref_mut())?
+            }
+            "status" => rom.status = Some(value),
+            "serial" => rom.serial = Some(value),
+            "header" => rom.header = Some(value),
+            _ => {}
+        }
+    }
+
+    if !has_name {
+        return Err(Error::new("missing attribute 'name'", path));
+    }
+
+    Ok(rom)
+}
+
+/// Some DATs leave hashes blank rather than leaving them out when a ROM hasn't been dumped.
+/// Malformed hashes are an error, unless `invalid` is given to collect them in.
+fn parse_hash<T>(
+    value: &str,
+    path: &str,
+    attr: &str,
+    invalid: Option<&mut Vec<Error>>,
+) -> Result<Option<T>, Error>
+where
+    T: FromStr<Err = ParseHashError>,
+{
+    if value.is_empty() {
+        return Ok(None);
+    }
+
+    match (value.parse(), invalid) {
+        (Ok(hash), _) => Ok(Some(hash)),
+        (Err(err), Some(invalid)) => {
+            invalid.push(Error::new(err, &format!("{path}.{attr}")));
+            Ok(None)
+        }
+        (Err(err), None) => Err(Error::new(err, &format!("{path}.{attr}"))),
+    }
+}
+
+fn clrmamepro_from_attributes(e: &BytesStart, path: &str) -> Result<ClrMamePro, Error> {
+    let mut force_no_dump = None;
+
+    for (key, value) in attributes(e, path)? {
+        if key == "forcenodump" {
+            force_no_dump = Some(match value.as_str() {
+                "obsolete" => ForceNoDump::Obsolete,
+                "required" => ForceNoDump::Required,
+                "ignore" => ForceNoDump::Ignore,
+                _ => {
+                    let message = format!("unknown forcenodump value '{value}'");
+                    return Err(Error::new(message, &format!("{path}.forcenodump")));
+                }
+            });
+        }
+    }
+
+    Ok(ClrMamePro { force_no_dump })
+}
+
+fn attributes(e: &BytesStart, path: &str) -> Result<Vec<(String, String)>, Error> {
+    e.attributes()
+        .map(|attr| {
+            let attr = attr.map_err(|err| Error::new(err, path))?;
+            let key = String::from_utf8_lossy(attr.key.local_name().as_ref()).into_owned();
+            let value = attr
+                .unescape_value()
+                .map_err(|err| Error::new(err, path))?
+                .into_owned();
+
+            Ok((key, value))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const MULTI_ROM: &str = r#"<?xml version="1.0"?>
+<datafile>
+    <header>
+        <name>Arcade</name>
+        <description>Multi-ROM games</description>
+    </header>
+    <machine name="puckman">
+        <description>Puck Man (Japan set 1)</description>
+        <year>1980</year>
+        <rom name="pm1_prg1.6e" size="2048" crc="f36e88ab" sha1="813cecf44bf5464b1aed64b36f5047e4c79ba176"/>
+        <rom name="pm1_prg2.6k" size="2048" crc="618bd9b3" sha1="b9ca52b63a49ddece768378d331deebbe34fe177"/>
+    </machine>
+    <game name="Empty"/>
+</datafile>"#;
+
+    #[test]
+    fn reads_header_before_games() {
+        let games = Games::new(MULTI_ROM.as_bytes()).unwrap();
+        assert_eq!(games.header().name, "Arcade");
+    }
+
+    #[test]
+    fn reads_games_lazily() {
+        let mut games = Games::new(MULTI_ROM.as_bytes()).unwrap();
+        let first = games.next().unwrap().unwrap();
+        assert_eq!(first.name, "puckman");
+        assert_eq!(first.description, "Puck Man (Japan set 1)");
+    }
+
+    #[test]
+    fn reads_multiple_roms() {
+        let dat = DataFile::from_reader(MULTI_ROM.as_bytes()).unwrap();
+        let game = dat.games.first().unwrap();
+        assert_eq!(game.roms.len(), 2);
+        assert_eq!(game.roms[1].name, "pm1_prg2.6k");
+        assert_eq!(game.roms[1].md5, None);
+    }
+
+    #[test]
+    fn reads_empty_games() {
+        let dat = DataFile::from_reader(MULTI_ROM.as_bytes()).unwrap();
+        assert_eq!(dat.games.len(), 2);
+        assert!(dat.games[1].roms.is_empty());
+    }
+
+    #[test]
+    fn reports_path_of_invalid_values() {
+        let xml = MULTI_ROM.replace(
+            r#"size="2048" crc="618bd9b3""#,
+            r#"size="big" crc="618bd9b3""#,
+        );
+        let err = DataFile::from_reader(xml.as_bytes()).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "invalid size 'big' - game[0].rom[1].size at 11:9"
+        );
+    }
+
+    #[test]
+    fn tracks_positions_of_non_empty_roms() {
+        let xml = MULTI_ROM.replace(
+            r#"sha1="813cecf44bf5464b1aed64b36f5047e4c79ba176"/>"#,
+            r#"sha1="813cecf44bf5464b1aed64b36f5047e4c79ba176"></rom>"#,
+        );
+        let mut games = Games::new(xml.as_bytes()).unwrap();
+        let game = games.next().unwrap().unwrap();
+        let lines = games
+            .rom_positions()
+            .iter()
+            .map(|position| position.line)
+            .collect::<Vec<_>>();
+
+        assert_eq!(game.roms.len(), 2);
+        assert_eq!(lines, vec![10, 11]);
+    }
+
+    #[test]
+    fn rejects_invalid_hashes() {
+        let xml = MULTI_ROM.replace(r#"crc="618bd9b3""#, r#"crc="148323542""#);
+        let err = DataFile::from_reader(xml.as_bytes()).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "invalid CRC '148323542', expected 8 hex digits - game[0].rom[1].crc at 11:9"
+        );
+    }
+
+    #[test]
+    fn skips_invalid_hashes_when_lenient() {
+        let xml = MULTI_ROM.replace(r#"crc="618bd9b3""#, r#"crc="148323542""#);
+        let mut games = Games::new(xml.as_bytes()).unwrap().lenient_hashes();
+        let game = games.next().unwrap().unwrap();
+        let invalid = games.take_invalid_hashes();
+
+        assert_eq!(game.roms[1].crc, None);
+        assert_eq!(invalid.len(), 1);
+        assert_eq!(
+            invalid[0].position().map(|position| position.line),
+            Some(11)
+        );
+    }
+
+    #[test]
+    fn reports_missing_header() {
+        let err = DataFile::from_reader(r#"<datafile><game name="x"/></datafile>"#.as_bytes())
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "expected a header, but found <game> - datafile at 1:11"
+        );
+    }
+}
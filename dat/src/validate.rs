@@ -0,0 +1,247 @@
+use crate::{Error, Game, Games, Position, Rom};
+use std::collections::HashMap;
+use std::fmt::{self, Display, Formatter};
+use std::io::BufRead;
+
+/// A problem with a DAT which doesn't stop it from being parsed
+#[derive(Clone, Debug)]
+pub struct Issue {
+    pub position: Position,
+    pub message: String,
+}
+
+impl Display for Issue {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.position, self.message)
+    }
+}
+
+/// Check a DAT for problems which a lenient parse would let through: malformed hashes, games
+/// which appear more than once, ROMs which appear in more than one game, and games without any
+/// ROMs. Reading stops at the first syntax error or other malformed value, which is returned as an
+/// `Err`.
+pub fn validate<R: BufRead>(reader: R) -> Result<Vec<Issue>, Error> {
+    let mut games = Games::new(reader)?.lenient_hashes();
+    let mut validator = Validator::default();
+
+    while let Some(game) = games.next() {
+        let game = game?;
+
+        for err in games.take_invalid_hashes() {
+            let position = err.position.unwrap_or(games.position());
+            validator.report(position, format!("{} - {}", err.message, err.path));
+        }
+
+        validator.check(&game, games.position(), games.rom_positions());
+    }
+
+    Ok(validator.issues)
+}
+
+/// The hashes of a ROM which has already been checked, by kind from strongest to weakest
+struct SeenRom {
+    game: String,
+    position: Position,
+    hashes: [Option<String>; 4],
+}
+
+const HASH_KINDS: [&str; 4] = ["sha256", "sha1", "md5", "crc"];
+
+#[derive(Default)]
+struct Validator {
+    issues: Vec<Issue>,
+    names: HashMap<String, Position>,
+    roms: Vec<SeenRom>,
+    /// Indexes into `roms`, by hash kind and value
+    hashes: HashMap<(&'static str, String), Vec<usize>>,
+}
+
+impl Validator {
+    fn check(&mut self, game: &Game, position: Position, rom_positions: &[Position]) {
+        if let Some(first) = self.names.get(&game.name) {
+            let message = format!("duplicate game '{}', first defined at {first}", game.name);
+            self.report(position, message);
+        } else {
+            self.names.insert(game.name.clone(), position);
+        }
+
+        if game.roms.is_empty() {
+            self.report(position, format!("game '{}' has no ROMs", game.name));
+        }
+
+        for (rom, &position) in game.roms.iter().zip(rom_positions) {
+            self.check_duplicate(game, rom, position);
+        }
+    }
+
+    /// ROMs are duplicates when every kind of hash they both have matches, so a ROM with only a
+    /// CRC is still compared against one with a CRC and a SHA-1, but two ROMs whose CRCs collide
+    /// aren't duplicates if their SHA-1s differ. Empty files are skipped as they always share the
+    /// same hashes.
+    fn check_duplicate(&mut self, game: &Game, rom: &Rom, position: Position) {
+        if rom.size == 0 {
+            return;
+        }
+
+        let hashes = [
+            rom.sha256.map(|hash| hash.to_string()),
+            rom.sha1.map(|hash| hash.to_string()),
+            rom.md5.map(|hash| hash.to_string()),
+            rom.crc.map(|hash| hash.to_string()),
+        ];
+
+        let mut candidates = HASH_KINDS
+            .iter()
+            .zip(&hashes)
+            .filter_map(|(&kind, hash)| self.hashes.get(&(kind, hash.clone()?)))
+            .flatten()
+            .copied()
+            .collect::<Vec<_>>();
+        candidates.sort_unstable();
+        candidates.dedup();
+
+        let duplicate = candidates.into_iter().find_map(|i| {
+            let other = &self.roms[i];
+            let kind = shared_kind(&hashes, &other.hashes)?;
+            (other.game != game.name).then_some((other, kind))
+        });
+
+        if let Some((other, kind)) = duplicate {
+            let message = format!(
+                "ROM '{}' has the same {kind} as a ROM in '{}' at {}",
+                rom.name, other.game, other.position
+            );
+            self.report(position, message);
+        }
+
+        let index = self.roms.len();
+        for (&kind, hash) in HASH_KINDS.iter().zip(&hashes) {
+            if let Some(hash) = hash {
+                self.hashes
+                    .entry((kind, hash.clone()))
+                    .or_default()
+                    .push(index);
+            }
+        }
+
+        self.roms.push(SeenRom {
+            game: game.name.clone(),
+            position,
+            hashes,
+        });
+    }
+
+    fn report(&mut self, position: Position, message: String) {
+        self.issues.push(Issue { position, message });
+    }
+}
+
+/// The strongest kind of hash two ROMs both have, as long as every kind they both have matches
+fn shared_kind(a: &[Option<String>; 4], b: &[Option<String>; 4]) -> Option<&'static str> {
+    let mut shared = None;
+
+    for ((&kind, a), b) in HASH_KINDS.iter().zip(a).zip(b) {
+        match (a, b) {
+            (Some(a), Some(b)) if a != b => return None,
+            (Some(_), Some(_)) => {
+                shared.get_or_insert(kind);
+            }
+            _ => {}
+        }
+    }
+
+    shared
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn issues(games: &str) -> Vec<String> {
+        let xml = format!("<datafile>\n<header><name>Test</name></header>\n{games}\n</datafile>");
+
+        validate(xml.as_bytes())
+            .unwrap()
+            .iter()
+            .map(|issue| issue.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn accepts_valid_games() {
+        let games = r#"<game name="A"><rom name="A.bin" size="1" crc="0123abcd" sha1="0123456789abcdef0123456789abcdef01234567"/></game>"#;
+        assert!(issues(games).is_empty());
+    }
+
+    #[test]
+    fn reports_invalid_hashes() {
+        let games = "<game name=\"A\"><rom name=\"A.bin\" size=\"1\" crc=\"148323542\"/></game>\n<game name=\"B\"><rom name=\"B.bin\" size=\"1\" md5=\"xyz\"/></game>";
+        assert_eq!(
+            issues(games),
+            vec![
+                "3:16: invalid CRC '148323542', expected 8 hex digits - game[0].rom[0].crc",
+                "4:16: invalid MD5 'xyz', expected 32 hex digits - game[1].rom[0].md5",
+            ]
+        );
+    }
+
+    #[test]
+    fn reports_duplicate_names() {
+        let games = "<game name=\"A\"><rom name=\"1\" size=\"1\"/></game>\n  <game name=\"A\"><rom name=\"2\" size=\"1\"/></game>";
+        assert_eq!(
+            issues(games),
+            vec!["4:3: duplicate game 'A', first defined at 3:1"]
+        );
+    }
+
+    #[test]
+    fn reports_duplicate_hashes() {
+        let games = "<game name=\"A\"><rom name=\"A.bin\" size=\"1\" crc=\"0123abcd\"/></game>\n<game name=\"B\"><rom name=\"B.bin\" size=\"1\" crc=\"0123ABCD\"/></game>";
+        assert_eq!(
+            issues(games),
+            vec!["4:16: ROM 'B.bin' has the same crc as a ROM in 'A' at 3:16"]
+        );
+    }
+
+    #[test]
+    fn reports_duplicate_hashes_of_different_kinds() {
+        let games = "<game name=\"A\"><rom name=\"A.bin\" size=\"1\" sha1=\"0123456789abcdef0123456789abcdef01234567\"/></game>\n<game name=\"B\"><rom name=\"B.bin\" size=\"1\" crc=\"0123abcd\" sha1=\"0123456789abcdef0123456789abcdef01234567\"/></game>\n<game name=\"C\"><rom name=\"C.bin\" size=\"1\" crc=\"0123abcd\"/></game>";
+        assert_eq!(
+            issues(games),
+            vec![
+                "4:16: ROM 'B.bin' has the same sha1 as a ROM in 'A' at 3:16",
+                "5:16: ROM 'C.bin' has the same crc as a ROM in 'B' at 4:16",
+            ]
+        );
+    }
+
+    #[test]
+    fn ignores_crc_collisions() {
+        let games = "<game name=\"A\"><rom name=\"A.bin\" size=\"1\" crc=\"0123abcd\" sha1=\"0123456789abcdef0123456789abcdef01234567\"/></game>\n<game name=\"B\"><rom name=\"B.bin\" size=\"1\" crc=\"0123abcd\" sha1=\"76543210fedcba9876543210fedcba9876543210\"/></game>";
+        assert!(issues(games).is_empty());
+    }
+
+    #[test]
+    fn reports_duplicate_hashes_of_non_empty_roms() {
+        let games = "<game name=\"A\"><rom name=\"A.bin\" size=\"1\" crc=\"0123abcd\"></rom></game>\n<game name=\"B\">\n<rom name=\"B1.bin\" size=\"1\" crc=\"11111111\"></rom>\n<rom name=\"B2.bin\" size=\"1\" crc=\"0123abcd\"></rom></game>";
+        assert_eq!(
+            issues(games),
+            vec!["6:1: ROM 'B2.bin' has the same crc as a ROM in 'A' at 3:16"]
+        );
+    }
+
+    #[test]
+    fn reports_empty_games() {
+        assert_eq!(
+            issues(r#"<game name="A"/>"#),
+            vec!["3:1: game 'A' has no ROMs"]
+        );
+    }
+
+    #[test]
+    fn reports_syntax_error_positions() {
+        let xml = "<datafile>\n<header><name>Test</name></header>\n<game name=\"A\"></rom></game>";
+        let err = validate(xml.as_bytes()).unwrap_err();
+        assert_eq!(err.position().map(|position| position.line), Some(3));
+    }
+}
@@ -0,0 +1,242 @@
+use crate::{DataFile, ForceNoDump, Game, Header, Rom};
+use std::io::{Result, Write};
+
+impl ForceNoDump {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Obsolete => "obsolete",
+            Self::Required => "required",
+            Self::Ignore => "ignore",
+        }
+    }
+}
+
+impl DataFile {
+    /// Write this DAT as No-Intro v3 XML, which is a superset of the Logiqx datafile format
+    pub fn write_xml<W: Write>(&self, w: &mut W) -> Result<()> {
+        writeln!(w, r#"<?xml version="1.0"?>"#)?;
+        writeln!(
+            w,
+            r#"<!DOCTYPE datafile PUBLIC "-//Logiqx//DTD ROM Management Datafile//EN" "http://www.logiqx.com/Dats/datafile.dtd">"#
+        )?;
+        writeln!(w, "<datafile>")?;
+        write_xml_header(w, &self.header)?;
+
+        for game in &self.games {
+            write_xml_game(w, game)?;
+        }
+
+        writeln!(w, "</datafile>")
+    }
+
+    /// Write this DAT in the ClrMamePro text format
+    pub fn write_clrmamepro<W: Write>(&self, w: &mut W) -> Result<()> {
+        let header = &self.header;
+
+        writeln!(w, "clrmamepro (")?;
+        writeln!(w, "\tname {}", quote(&header.name))?;
+        writeln!(w, "\tdescription {}", quote(&header.description))?;
+        writeln!(w, "\tversion {}", quote(&header.version))?;
+        writeln!(w, "\tauthor {}", quote(&header.author))?;
+        writeln!(w, "\thomepage {}", quote(&header.homepage))?;
+        writeln!(w, "\turl {}", quote(&header.url))?;
+
+        if let Some(force_no_dump) = header.clrmamepro.force_no_dump {
+            writeln!(w, "\tforcenodump {}", force_no_dump.as_str())?;
+        }
+
+        writeln!(w, ")")?;
+
+        for game in &self.games {
+            writeln!(w)?;
+            writeln!(w, "game (")?;
+            writeln!(w, "\tname {}", quote(&game.name))?;
+            writeln!(w, "\tdescription {}", quote(&game.description))?;
+            for rom in &game.roms {
+                writeln!(w, "\trom ( {} )", clrmamepro_rom(rom))?;
+            }
+            writeln!(w, ")")?;
+        }
+
+        Ok(())
+    }
+}
+
+fn write_xml_header<W: Write>(w: &mut W, header: &Header) -> Result<()> {
+    writeln!(w, "\t<header>")?;
+    writeln!(w, "\t\t<id>{}</id>", header.id)?;
+    writeln!(w, "\t\t<name>{}</name>", escape(&header.name))?;
+    writeln!(
+        w,
+        "\t\t<description>{}</description>",
+        escape(&header.description)
+    )?;
+    writeln!(w, "\t\t<version>{}</version>", escape(&header.version))?;
+    writeln!(w, "\t\t<author>{}</author>", escape(&header.author))?;
+    writeln!(w, "\t\t<homepage>{}</homepage>", escape(&header.homepage))?;
+    writeln!(w, "\t\t<url>{}</url>", escape(&header.url))?;
+
+    if let Some(subset) = &header.subset {
+        writeln!(w, "\t\t<subset>{}</subset>", escape(subset))?;
+    }
+
+    match header.clrmamepro.force_no_dump {
+        Some(force_no_dump) => writeln!(
+            w,
+            r#"		<clrmamepro forcenodump="{}"/>"#,
+            force_no_dump.as_str()
+        )?,
+        None => writeln!(w, "\t\t<clrmamepro/>")?,
+    }
+
+    writeln!(w, "\t</header>")
+}
+
+fn write_xml_game<W: Write>(w: &mut W, game: &Game) -> Result<()> {
+    writeln!(w, r#"	<game name="{}">"#, escape(&game.name))?;
+    writeln!(
+        w,
+        "\t\t<description>{}</description>",
+        escape(&game.description)
+    )?;
+
+    for rom in &game.roms {
+        write_xml_rom(w, rom)?;
+    }
+
+    writeln!(w, "\t</game>")
+}
+
+fn write_xml_rom<W: Write>(w: &mut W, rom: &Rom) -> Result<()> {
+    write!(
+        w,
+        r#"		<rom name="{}" size="{}""#,
+        escape(&rom.name),
+        rom.size
+    )?;
+
+    let optional = [
+        ("crc", rom.crc.map(|crc| crc.to_string())),
+        ("md5", rom.md5.map(|md5| md5.to_string())),
+        ("sha1", rom.sha1.map(|sha1| sha1.to_string())),
+        ("sha256", rom.sha256.map(|sha256| sha256.to_string())),
+        ("status", rom.status.clone()),
+        ("serial", rom.serial.clone()),
+        ("header", rom.header.clone()),
+    ];
+
+    for (name, value) in optional {
+        if let Some(value) = value {
+            write!(w, r#" {name}="{}""#, escape(&value))?;
+        }
+    }
+
+    writeln!(w, "/>")
+}
+
+fn clrmamepro_rom(rom: &Rom) -> String {
+    let mut fields = vec![
+        format!("name {}", quote(&rom.name)),
+        format!("size {}", rom.size),
+    ];
+
+    if let Some(crc) = rom.crc {
+        fields.push(format!("crc {crc}"));
+    }
+
+    if let Some(md5) = rom.md5 {
+        fields.push(format!("md5 {md5}"));
+    }
+
+    if let Some(sha1) = rom.sha1 {
+        fields.push(format!("sha1 {sha1}"));
+    }
+
+    if let Some(sha256) = rom.sha256 {
+        fields.push(format!("sha256 {sha256}"));
+    }
+
+    if let Some(status) = &rom.status {
+        fields.push(format!("flags {status}"));
+    }
+
+    if let Some(serial) = &rom.serial {
+        fields.push(format!("serial {}", quote(serial)));
+    }
+
+    fields.join(" ")
+}
+
+fn escape(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+
+    for c in s.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            c => escaped.push(c),
+        }
+    }
+
+    escaped
+}
+
+/// ClrMamePro has no escape sequences, so embedded quotes are replaced instead
+fn quote(s: &str) -> String {
+    format!("\"{}\"", s.replace('"', "'"))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::fs::read_to_string;
+
+    #[test]
+    fn xml_round_trips() {
+        let dat = v3_sample();
+        let mut out = Vec::new();
+        dat.write_xml(&mut out).unwrap();
+
+        let parsed = DataFile::from_file(std::str::from_utf8(&out).unwrap()).unwrap();
+        assert_eq!(parsed.header.name, dat.header.name);
+        assert_eq!(parsed.header.clrmamepro, dat.header.clrmamepro);
+        assert_eq!(parsed.games.len(), dat.games.len());
+        assert_eq!(parsed.games[0].name, dat.games[0].name);
+        assert_eq!(parsed.games[0].roms[0].sha1, dat.games[0].roms[0].sha1);
+        assert_eq!(parsed.games[0].roms[0].sha256, dat.games[0].roms[0].sha256);
+    }
+
+    #[test]
+    fn xml_escapes_names() {
+        let mut dat = v3_sample();
+        dat.games[0].name = "Tom & Jerry <\"Beta\">".to_owned();
+        let mut out = Vec::new();
+        dat.write_xml(&mut out).unwrap();
+
+        let parsed = DataFile::from_file(std::str::from_utf8(&out).unwrap()).unwrap();
+        assert_eq!(parsed.games[0].name, "Tom & Jerry <\"Beta\">");
+    }
+
+    #[test]
+    fn writes_clrmamepro_games() {
+        let dat = v3_sample();
+        let mut out = Vec::new();
+        dat.write_clrmamepro(&mut out).unwrap();
+        let out = String::from_utf8(out).unwrap();
+
+        assert!(out.starts_with("clrmamepro (\n\tname \"Rust DAT parser\"\n"));
+        assert!(out.contains("\tforcenodump required\n"));
+        assert!(out.contains("\tname \"Example Game\"\n"));
+        assert!(out.contains(
+            "\trom ( name \"Example Game.txt\" size 5432 crc 08d73cd6 md5 1a79a4d60de6718e8e5b326e338ae533"
+        ));
+    }
+
+    fn v3_sample() -> DataFile {
+        let txt = read_to_string("samples/v3.dat").unwrap();
+        DataFile::from_file(&txt).unwrap()
+    }
+}
@@ -1,13 +1,23 @@
-use futures::{stream::once, Stream, StreamExt, TryStreamExt};
+use futures::{stream::unfold, Stream};
 use std::{
+    error::Error,
+    ffi::OsStr,
+    fmt::{self, Display, Formatter},
     fs::Metadata,
-    io::Result,
+    io::{self, Result},
     path::{Path, PathBuf},
+    sync::Arc,
 };
-use tokio::fs::{metadata, read_dir, DirEntry};
-use tokio_stream::wrappers::ReadDirStream;
+use tokio::fs::{metadata, read_dir, symlink_metadata};
 
-type MetaResult = Result<FileMeta>;
+type MetaResult = std::result::Result<FileMeta, WalkError>;
+
+/// Decides whether an entry is yielded from its path and whether it's a directory. Directories
+/// which are rejected aren't descended into.
+type Filter = Arc<dyn Fn(&Path, bool) -> bool + Send + Sync>;
+
+/// Identifies a directory regardless of the path it was reached through
+type DirId = (u64, u64);
 
 pub struct FileMeta {
     pub depth: usize,
@@ -15,37 +25,411 @@ pub struct FileMeta {
     pub path: PathBuf,
 }
 
-impl FileMeta {
-    async fn from_dir_entry(entry: DirEntry, depth: usize) -> Result<Self> {
-        let path = entry.path();
-        let meta = metadata(&path).await?;
+/// An entry which couldn't be read, such as a directory without permission to list it or a
+/// broken symlink. Walking carries on past these.
+#[derive(Debug)]
+pub struct WalkError {
+    pub path: PathBuf,
+    pub source: io::Error,
+}
 
-        Ok(Self { meta, depth, path })
+impl WalkError {
+    fn new(path: &Path, source: io::Error) -> Self {
+        Self {
+            path: path.to_path_buf(),
+            source,
+        }
     }
 }
 
-pub async fn walk<P: Into<PathBuf>>(path: P) -> Result<impl Stream<Item = MetaResult>> {
-    Ok(dir(path.into(), 0)
-        .await?
-        .and_then(dir_or_singleton)
-        .boxed()
-        .try_flatten())
+impl Display for WalkError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.path.display(), self.source)
+    }
+}
+
+impl Error for WalkError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(&self.source)
+    }
+}
+
+/// How a directory is walked. By default every entry is yielded in the order the file system
+/// returns them, including hidden files, and symlinks are followed.
+#[derive(Clone)]
+pub struct WalkOptions {
+    min_depth: usize,
+    max_depth: Option<usize>,
+    follow_links: bool,
+    hidden: bool,
+    sorted: bool,
+    filter: Option<Filter>,
+}
+
+impl Default for WalkOptions {
+    fn default() -> Self {
+        Self {
+            min_depth: 0,
+            max_depth: None,
+            follow_links: true,
+            hidden: true,
+            sorted: false,
+            filter: None,
+        }
+    }
+}
+
+impl WalkOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Don't yield entries shallower than `depth`. Entries directly inside the walked directory
+    /// have a depth of 0.
+    pub fn min_depth(mut self, depth: usize) -> Self {
+        self.min_depth = depth;
+        self
+    }
+
+    /// Don't descend any deeper than `depth`
+    pub fn max_depth(mut self, depth: usize) -> Self {
+        self.max_depth = Some(depth);
+        self
+    }
+
+    /// Whether to descend into symlinked directories. A symlink which leads back to one of its
+    /// own parents is yielded, but not descended into.
+    pub fn follow_links(mut self, follow_links: bool) -> Self {
+        self.follow_links = follow_links;
+        self
+    }
+
+    /// Whether to yield files and directories whose names start with a dot
+    pub fn hidden(mut self, hidden: bool) -> Self {
+        self.hidden = hidden;
+        self
+    }
+
+    /// Yield the entries of each directory sorted by name, so output is the same on every run
+    pub fn sorted(mut self, sorted: bool) -> Self {
+        self.sorted = sorted;
+        self
+    }
+
+    /// Skip any entries which `filter` rejects, along with everything below them. The filter is
+    /// given each entry's path and whether it's a directory, and is applied even to entries which
+    /// can't be read, so that those are skipped instead of being yielded as errors.
+    pub fn filter<F>(mut self, filter: F) -> Self
+    where
+        F: Fn(&Path, bool) -> bool + Send + Sync + 'static,
+    {
+        self.filter = Some(Arc::new(filter));
+        self
+    }
+
+    /// Walk a directory depth-first, yielding each directory before its contents. Only failing to
+    /// read the directory itself is an error here; entries which can't be read are yielded as a
+    /// `WalkError`, and the walk carries on past them.
+    pub async fn walk<P: Into<PathBuf>>(self, path: P) -> Result<impl Stream<Item = MetaResult>> {
+        let path = path.into();
+        let id = dir_id(&metadata(&path).await?);
+        let entries = self.read_dir(&path, 0).await?;
+        let walker = Walker {
+            options: self,
+            stack: vec![Frame { id, entries }],
+        };
+
+        Ok(unfold(walker, |mut walker| async move {
+            let item = walker.next().await?;
+            Some((item, walker))
+        }))
+    }
+
+    /// Read the entries of a directory, in the reverse of the order they should be yielded
+    async fn read_dir(&self, dir: &Path, depth: usize) -> Result<Vec<MetaResult>> {
+        let mut reader = read_dir(dir).await?;
+        let mut entries = Vec::new();
+
+        while let Some(entry) = reader.next_entry().await? {
+            if !self.hidden && is_hidden(&entry.file_name()) {
+                continue;
+            }
+
+            let path = entry.path();
+            let meta = self.metadata(&path).await;
+
+            let accepted = match &self.filter {
+                Some(filter) => {
+                    // Entries which can't be read still say what type they are, unless they're a
+                    // symlink to something which can't be read
+                    let is_dir = match &meta {
+                        Ok(meta) => meta.is_dir(),
+                        Err(_) => entry.file_type().await.is_ok_and(|t| t.is_dir()),
+                    };
+                    filter(&path, is_dir)
+                }
+                None => true,
+            };
+
+            let file = match meta {
+                Ok(meta) => Ok(FileMeta {
+                    depth,
+                    meta,
+                    path: path.clone(),
+                }),
+                Err(err) => Err(WalkError::new(&path, err)),
+            };
+
+            if accepted {
+                entries.push((path, file));
+            }
+        }
+
+        if self.sorted {
+            entries.sort_unstable_by(|(a, _), (b, _)| b.cmp(a));
+        } else {
+            entries.reverse();
+        }
+
+        Ok(entries.into_iter().map(|(_, file)| file).collect())
+    }
+
+    async fn metadata(&self, path: &Path) -> Result<Metadata> {
+        if self.follow_links {
+            metadata(path).await
+        } else {
+            symlink_metadata(path).await
+        }
+    }
 }
 
-async fn dir<P: AsRef<Path>>(path: P, depth: usize) -> Result<impl Stream<Item = MetaResult>> {
-    let entries = read_dir(path).await?;
-    Ok(ReadDirStream::new(entries).and_then(move |entry| FileMeta::from_dir_entry(entry, depth)))
+/// The entries of a directory which are yet to be yielded
+struct Frame {
+    id: Option<DirId>,
+    entries: Vec<MetaResult>,
 }
 
-async fn dir_or_singleton(file: FileMeta) -> Result<impl Stream<Item = MetaResult>> {
-    let is_dir = file.meta.is_dir();
+struct Walker {
+    options: WalkOptions,
+    stack: Vec<Frame>,
+}
+
+impl Walker {
+    async fn next(&mut self) -> Option<MetaResult> {
+        loop {
+            let frame = self.stack.last_mut()?;
+            let Some(entry) = frame.entries.pop() else {
+                self.stack.pop();
+                continue;
+            };
+
+            let file = match entry {
+                Ok(file) => file,
+                Err(err) => return Some(Err(err)),
+            };
+
+            if self.should_descend(&file) {
+                let id = dir_id(&file.meta);
+                let entries = match self.options.read_dir(&file.path, file.depth + 1).await {
+                    Ok(entries) => entries,
+                    Err(err) => vec![Err(WalkError::new(&file.path, err))],
+                };
+
+                self.stack.push(Frame { id, entries });
+            }
+
+            if file.depth >= self.options.min_depth {
+                return Some(Ok(file));
+            }
+        }
+    }
+
+    fn should_descend(&self, file: &FileMeta) -> bool {
+        if !file.meta.is_dir() {
+            return false;
+        }
+
+        if self.options.max_depth.is_some_and(|max| file.depth >= max) {
+            return false;
+        }
+
+        match dir_id(&file.meta) {
+            Some(id) => !self.stack.iter().any(|frame| frame.id == Some(id)),
+            None => true,
+        }
+    }
+}
+
+fn is_hidden(name: &OsStr) -> bool {
+    name.as_encoded_bytes().starts_with(b".")
+}
+
+#[cfg(unix)]
+fn dir_id(meta: &Metadata) -> Option<DirId> {
+    use std::os::unix::fs::MetadataExt;
+    Some((meta.dev(), meta.ino()))
+}
+
+#[cfg(not(unix))]
+fn dir_id(_meta: &Metadata) -> Option<DirId> {
+    None
+}
+
+pub async fn walk<P: Into<PathBuf>>(path: P) -> Result<impl Stream<Item = MetaResult>> {
+    WalkOptions::new().walk(path).await
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use futures::StreamExt;
+    use std::fs;
+    use tempfile::TempDir;
+
+    /// a/
+    ///   b/
+    ///     c.bin
+    ///   d.bin
+    /// .hidden
+    /// e.bin
+    fn tree() -> TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("a/b")).unwrap();
+        fs::write(dir.path().join("a/b/c.bin"), "c").unwrap();
+        fs::write(dir.path().join("a/d.bin"), "d").unwrap();
+        fs::write(dir.path().join(".hidden"), "").unwrap();
+        fs::write(dir.path().join("e.bin"), "e").unwrap();
+        dir
+    }
+
+    /// Walk a directory, giving each entry's path relative to it, or an error's path prefixed
+    /// with `!`
+    async fn paths(options: WalkOptions, dir: &Path) -> Vec<String> {
+        options
+            .walk(dir)
+            .await
+            .unwrap()
+            .map(|entry| match entry {
+                Ok(file) => relative(&file.path, dir),
+                Err(err) => format!("!{}", relative(&err.path, dir)),
+            })
+            .collect()
+            .await
+    }
+
+    fn relative(path: &Path, dir: &Path) -> String {
+        path.strip_prefix(dir)
+            .unwrap()
+            .to_string_lossy()
+            .into_owned()
+    }
+
+    #[tokio::test]
+    async fn walks_depth_first_in_order() {
+        let dir = tree();
+        let paths = paths(WalkOptions::new().sorted(true), dir.path()).await;
+        assert_eq!(
+            paths,
+            vec![".hidden", "a", "a/b", "a/b/c.bin", "a/d.bin", "e.bin"]
+        );
+    }
+
+    #[tokio::test]
+    async fn limits_depth() {
+        let dir = tree();
+
+        let shallow = WalkOptions::new().sorted(true).max_depth(0);
+        assert_eq!(
+            paths(shallow, dir.path()).await,
+            vec![".hidden", "a", "e.bin"]
+        );
+
+        let deep = WalkOptions::new().sorted(true).min_depth(1);
+        assert_eq!(
+            paths(deep, dir.path()).await,
+            vec!["a/b", "a/b/c.bin", "a/d.bin"]
+        );
+    }
+
+    #[tokio::test]
+    async fn skips_hidden_files() {
+        let dir = tree();
+        let options = WalkOptions::new().sorted(true).hidden(false).max_depth(0);
+        assert_eq!(paths(options, dir.path()).await, vec!["a", "e.bin"]);
+    }
+
+    #[tokio::test]
+    async fn skips_filtered_directories() {
+        let dir = tree();
+        let options = WalkOptions::new()
+            .sorted(true)
+            .filter(|path, _| !path.ends_with("b"));
+        assert_eq!(
+            paths(options, dir.path()).await,
+            vec![".hidden", "a", "a/d.bin", "e.bin"]
+        );
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn stops_at_symlink_cycles() {
+        let dir = tree();
+        std::os::unix::fs::symlink(dir.path().join("a"), dir.path().join("a/b/loop")).unwrap();
+
+        let options = WalkOptions::new().sorted(true).min_depth(1);
+        assert_eq!(
+            paths(options, dir.path()).await,
+            vec!["a/b", "a/b/c.bin", "a/b/loop", "a/d.bin"]
+        );
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn yields_unreadable_entries_as_errors() {
+        let dir = tree();
+        std::os::unix::fs::symlink(dir.path().join("missing"), dir.path().join("broken")).unwrap();
+
+        let options = WalkOptions::new().sorted(true).max_depth(0);
+        assert_eq!(
+            paths(options, dir.path()).await,
+            vec![".hidden", "a", "!broken", "e.bin"]
+        );
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn filters_unreadable_entries() {
+        let dir = tree();
+        fs::create_dir(dir.path().join("skipped")).unwrap();
+        std::os::unix::fs::symlink(
+            dir.path().join("missing"),
+            dir.path().join("skipped/broken"),
+        )
+        .unwrap();
+
+        let options = WalkOptions::new()
+            .sorted(true)
+            .min_depth(1)
+            .filter(|path, _| !path.ends_with("broken"));
+        assert_eq!(
+            paths(options, dir.path()).await,
+            vec!["a/b", "a/b/c.bin", "a/d.bin"]
+        );
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn reads_broken_symlinks_when_not_following_links() {
+        let dir = tree();
+        std::os::unix::fs::symlink(dir.path().join("missing"), dir.path().join("broken")).unwrap();
 
-    if is_dir {
-        let path = file.path.clone();
-        let rest = dir(path, file.depth + 1).await?;
-        let stream = once(async { Ok(file) });
-        Ok(stream.chain(rest).boxed())
-    } else {
-        Ok(once(async { Ok(file) }).boxed())
+        let options = WalkOptions::new()
+            .sorted(true)
+            .max_depth(0)
+            .follow_links(false);
+        assert_eq!(
+            paths(options, dir.path()).await,
+            vec![".hidden", "a", "broken", "e.bin"]
+        );
     }
 }
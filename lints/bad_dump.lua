@@ -0,0 +1,47 @@
+requires = { "path", "file_db" }
+description = "Bad dumps with a verified alternative"
+help = "A file matches a DAT entry marked as a bad dump, while another version of the same game has been verified. Replace it with the verified dump."
+
+function has_status(game, status)
+    for _,rom in ipairs(game.roms) do
+        if rom.status == status then
+            return true
+        end
+    end
+
+    return false
+end
+
+function is_verified(game)
+    if #game.roms == 0 then
+        return false
+    end
+
+    for _,rom in ipairs(game.roms) do
+        if rom.status ~= "verified" then
+            return false
+        end
+    end
+
+    return true
+end
+
+-- The first tag after the title, like "USA" in "Game (USA) (Rev 1)"
+function region(name)
+    return string.match(name, "%((.-)%)")
+end
+
+function lint(file, api)
+    local game = api.db_by_name(file.path().stem)
+
+    if game == nil or not has_status(game, "baddump") then
+        return
+    end
+
+    for _,alternative in ipairs(api.db_alternatives(game.name)) do
+        if region(alternative.name) == region(game.name) and is_verified(alternative) then
+            local msg = string.format("bad dump; a verified dump is available as '%s'", alternative.name)
+            api.throw(msg)
+        end
+    end
+end
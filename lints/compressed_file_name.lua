@@ -1,4 +1,6 @@
 requires = { "path", "archive" }
+description = "Archived file names match their archive"
+help = "The file inside an archive should have the same name as the archive itself, apart from the extension."
 
 function contains(haystack, needle)
     for _,v in pairs(haystack) do
@@ -0,0 +1,74 @@
+requires = {}
+description = "Content is only stored once"
+help = "The same content is stored in more than one file of the system, either as a file or inside an archive. Run `romlint dupes` to find duplicates across every system."
+
+-- Files which can't be read are reported by the io lint
+local function contents_of(file, api)
+    local ok, contents = pcall(api.contents, file.path)
+    if ok then
+        return contents
+    end
+
+    return {}
+end
+
+function lint_collection(files, api)
+    -- Zips record the size and CRC of each member, so they are cheap to read. Other files have to
+    -- be hashed, so that only happens when another file or member has the same size.
+    local contents_by_file = {}
+    local size_counts = {}
+
+    for _, file in ipairs(files) do
+        if file.extension == "zip" then
+            local contents = contents_of(file, api)
+            contents_by_file[file.path] = contents
+            for _, content in ipairs(contents) do
+                size_counts[content.size] = (size_counts[content.size] or 0) + 1
+            end
+        else
+            size_counts[file.size] = (size_counts[file.size] or 0) + 1
+        end
+    end
+
+    local by_content = {}
+    local order = {}
+
+    for _, file in ipairs(files) do
+        local contents = contents_by_file[file.path]
+        if contents == nil then
+            contents = {}
+            if size_counts[file.size] > 1 then
+                contents = contents_of(file, api)
+            end
+        end
+
+        for _, content in ipairs(contents) do
+            -- Empty files are always identical
+            if content.size > 0 then
+                local key = content.size .. ":" .. content.crc
+                if by_content[key] == nil then
+                    by_content[key] = {}
+                    table.insert(order, key)
+                end
+
+                local name = file.name
+                if content.member ~= nil then
+                    name = file.name .. ": " .. content.member
+                end
+
+                table.insert(by_content[key], { path = file.path, name = name })
+            end
+        end
+    end
+
+    for _, key in ipairs(order) do
+        local group = by_content[key]
+        for _, copy in ipairs(group) do
+            for _, other in ipairs(group) do
+                if other ~= copy and other.path ~= copy.path then
+                    api.report(copy.path, string.format("same content as '%s'", other.name))
+                end
+            end
+        end
+    end
+end
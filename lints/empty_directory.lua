@@ -0,0 +1,9 @@
+requires = {}
+description = "Directories aren't empty"
+help = "Empty directories are usually left behind after moving files around, and can be deleted."
+
+function lint_dir(dir, api)
+    if #dir.entries == 0 then
+        api.throw("directory is empty")
+    end
+end
@@ -1,4 +1,6 @@
 requires = { "stat" }
+description = "Files and directories have standard permissions"
+help = "Files should have mode 644 and directories mode 755."
 
 function lint(file, api)
     local stat = file.stat()
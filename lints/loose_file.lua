@@ -1,4 +1,6 @@
 requires = { "path" }
+description = "Files have a known extension"
+help = "Every file should use one of the system's configured raw, obsolete or archive formats."
 
 function concat(...)
     local arg = {...}
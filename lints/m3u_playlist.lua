@@ -0,0 +1,67 @@
+requires = { "playlist", "file_db" }
+description = "Playlists list every disc in order"
+help = "Each entry of an .m3u playlist should exist next to it, and the discs of the game should all be listed in order."
+
+function disc_number(name)
+    return tonumber(string.match(name, "%(Disc (%d+)%)"))
+end
+
+function with_disc(name, number)
+    return (string.gsub(name, "%(Disc %d+%)", "(Disc " .. number .. ")"))
+end
+
+function lint(file, api)
+    local playlist = file.m3u()
+
+    if playlist == nil then
+        return
+    end
+
+    local missing = {}
+    local previous = 0
+    for _, entry in ipairs(playlist.entries) do
+        if not entry.exists then
+            table.insert(missing, entry.name)
+        end
+
+        local number = disc_number(entry.name)
+        if number ~= nil then
+            if number <= previous then
+                api.throw(string.format("discs are out of order at '%s'", entry.name))
+            end
+            previous = number
+        end
+    end
+
+    if #missing > 0 then
+        api.throw(string.format("missing playlist entries: %s", table.concat(missing, ", ")))
+    end
+
+    local first = playlist.entries[1]
+    if first == nil or disc_number(first.name) == nil then
+        return
+    end
+
+    -- Count the set's discs in the DAT, which are numbered from 1 without gaps
+    local stem = string.match(first.name, "^(.*)%.[^.]+$") or first.name
+    local listed = {}
+    for _, entry in ipairs(playlist.entries) do
+        local number = disc_number(entry.name)
+        if number ~= nil then
+            listed[number] = true
+        end
+    end
+
+    local absent = {}
+    local number = 1
+    while api.db_by_name(with_disc(stem, number)) ~= nil do
+        if not listed[number] then
+            table.insert(absent, with_disc(stem, number))
+        end
+        number = number + 1
+    end
+
+    if #absent > 0 then
+        api.throw(string.format("playlist is missing discs: %s", table.concat(absent, ", ")))
+    end
+end
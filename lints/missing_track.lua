@@ -0,0 +1,22 @@
+requires = { "cue" }
+description = "Sheets reference existing files"
+help = "Every file named by a cue or GDI sheet should sit next to the sheet, or the disc can't be loaded."
+
+function lint(file, api)
+    local sheet = file.cue()
+
+    if sheet == nil then
+        return
+    end
+
+    local missing = {}
+    for _,f in ipairs(sheet.files) do
+        if not f.exists then
+            table.insert(missing, f.name)
+        end
+    end
+
+    if #missing > 0 then
+        api.throw(string.format("missing track files: %s", table.concat(missing, ", ")))
+    end
+end
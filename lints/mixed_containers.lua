@@ -0,0 +1,45 @@
+requires = {}
+description = "Each game is only stored once"
+help = "The same game shouldn't be kept in more than one container, like both a .zip and a .7z. Keep whichever matches the system's archive format."
+
+-- Only containers are compared, since a cue sheet, its tracks and an m3u playlist are expected to
+-- share a name
+local containers = { zip = true, ["7z"] = true, rar = true, chd = true, rvz = true, cso = true, gcz = true, wia = true, wbfs = true }
+
+function is_container(file, api)
+    if file.extension == nil then
+        return false
+    end
+
+    local ext = string.lower(file.extension)
+    for _, format in ipairs(api.config.archive_format) do
+        if ext == string.lower(format) then
+            return true
+        end
+    end
+
+    return containers[ext] == true
+end
+
+function lint_collection(files, api)
+    local by_stem = {}
+
+    for _, file in ipairs(files) do
+        if file.stem ~= nil and is_container(file, api) then
+            by_stem[file.stem] = by_stem[file.stem] or {}
+            table.insert(by_stem[file.stem], file)
+        end
+    end
+
+    for _, group in pairs(by_stem) do
+        if #group > 1 then
+            for _, file in ipairs(group) do
+                for _, other in ipairs(group) do
+                    if other ~= file then
+                        api.report(file.path, string.format("also stored as '%s'", other.name))
+                    end
+                end
+            end
+        end
+    end
+end
@@ -1,4 +1,6 @@
 requires = { "archive" }
+description = "Archives contain a single file"
+help = "Each archive should hold exactly one ROM."
 
 function lint(file, api)
     local files = file.archive().files
@@ -1,4 +1,6 @@
 requires = { "path" }
+description = "Files are not in an obsolete format"
+help = "The file uses a format listed in the system's obsolete_formats, and should be converted."
 
 function lint(file, api)
     local obsolete = api.config.obsolete_formats
@@ -0,0 +1,28 @@
+requires = {}
+description = "Track files belong to a sheet"
+help = "Bin and raw track files which no cue or GDI sheet in the same directory refers to are usually left over from a renamed or deleted disc."
+
+local track_extensions = { bin = true, raw = true }
+
+function lint_dir(dir, api)
+    local referenced = {}
+    for _,entry in ipairs(dir.entries) do
+        if entry.sheet ~= nil then
+            for _,f in ipairs(entry.sheet.files) do
+                referenced[f.name] = true
+            end
+        end
+    end
+
+    local stray = {}
+    for _,entry in ipairs(dir.entries) do
+        local ext = string.lower(string.match(entry.name, "%.([^.]+)$") or "")
+        if entry.is_file and track_extensions[ext] and not referenced[entry.name] then
+            table.insert(stray, entry.name)
+        end
+    end
+
+    if #stray > 0 then
+        api.throw(string.format("track files not referenced by any sheet: %s", table.concat(stray, ", ")))
+    end
+end
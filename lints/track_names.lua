@@ -0,0 +1,57 @@
+requires = { "path", "cue", "file_db" }
+description = "Track files match the DAT"
+help = "The files named by a cue or GDI sheet should be the tracks listed for the game in the system's DATs."
+
+function is_sheet(name)
+    local ext = string.lower(string.match(name, "%.([^.]+)$") or "")
+    return ext == "cue" or ext == "gdi"
+end
+
+function lint(file, api)
+    local sheet = file.cue()
+
+    if sheet == nil then
+        return
+    end
+
+    -- Unknown games are reported by unknown_file
+    local game = api.db_by_name(file.path().stem)
+    if game == nil then
+        return
+    end
+
+    local expected = {}
+    for _,rom in ipairs(game.roms) do
+        if not is_sheet(rom.name) then
+            expected[rom.name] = true
+        end
+    end
+
+    local unexpected = {}
+    for _,f in ipairs(sheet.files) do
+        if expected[f.name] then
+            expected[f.name] = nil
+        else
+            table.insert(unexpected, f.name)
+        end
+    end
+
+    local missing = {}
+    for name,_ in pairs(expected) do
+        table.insert(missing, name)
+    end
+    table.sort(missing)
+
+    local problems = {}
+    if #unexpected > 0 then
+        table.insert(problems, string.format("tracks not in the DAT: %s", table.concat(unexpected, ", ")))
+    end
+
+    if #missing > 0 then
+        table.insert(problems, string.format("sheet is missing tracks: %s", table.concat(missing, ", ")))
+    end
+
+    if #problems > 0 then
+        api.throw(table.concat(problems, "; "))
+    end
+end
@@ -1,4 +1,6 @@
 requires = { "path" }
+description = "ROMs are archived"
+help = "ROMs in a raw format should be compressed using the system's archive format."
 
 function contains(haystack, needle)
     for _,v in pairs(haystack) do
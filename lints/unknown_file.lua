@@ -1,4 +1,6 @@
 requires = { "path", "file_db" }
+description = "Files appear in a DAT"
+help = "The file's name doesn't match any entry in the system's DATs. It may be misnamed, or not part of the set."
 
 function lint(file, api)
     local stem = file.path().stem
@@ -30,23 +30,199 @@ impl Args {
     pub fn config_path(&self) -> PathBuf {
         self.cwd().join("romlint.toml")
     }
+
+    /// The directory containing ROMs to operate on: either a single system's directory or the
+    /// whole collection
+    pub fn scan_dir(&self) -> PathBuf {
+        match &self.system {
+            Some(system) => self.cwd().join(system),
+            None => self.cwd(),
+        }
+    }
 }
 
 /// The command to run
 #[derive(Clone, Debug, Subcommand)]
 pub enum Command {
     /// Dump all known ROM names to stdout
-    Dump,
+    Dump(DumpArgs),
+    /// Generate a DAT describing every ROM in a directory
+    DatCreate(DatCreateArgs),
+    /// Compare two versions of a DAT
+    DatDiff(DatDiffArgs),
+    /// Check a DAT for malformed hashes, duplicate entries and empty games
+    DatCheck(DatCheckArgs),
+    /// Rename local files to follow renames between two versions of a DAT
+    SyncNames(SyncNamesArgs),
+    /// List the games in each system's DATs which are missing from the collection
+    Missing(MissingArgs),
+    /// List content which is stored more than once in the collection
+    Dupes(DupesArgs),
+    /// Write .m3u playlists for games which span more than one disc
+    M3u(M3uArgs),
     /// Run lints against local ROMs
     Lint(LintArgs),
 }
 
+#[derive(Clone, Debug, ClapArgs)]
+pub struct DumpArgs {
+    /// Print the DAT each ROM name came from alongside it, separated by a tab
+    #[clap(long, default_value_t = false)]
+    pub show_source: bool,
+}
+
+#[derive(Clone, Debug, ClapArgs)]
+pub struct DatCreateArgs {
+    /// The directory containing ROMs to describe
+    pub dir: String,
+
+    /// The name of the DAT. If unset, the name of the directory is used
+    #[clap(long)]
+    pub name: Option<String>,
+
+    /// A description of the DAT. If unset, the name is used
+    #[clap(long)]
+    pub description: Option<String>,
+
+    /// The version of the DAT
+    #[clap(long, default_value = "1")]
+    pub version: String,
+
+    /// The author of the DAT
+    #[clap(long, default_value = "romlint")]
+    pub author: String,
+
+    /// Which DAT format to write
+    #[clap(long, default_value_t = DatFormat::Xml)]
+    #[arg(value_enum)]
+    pub format: DatFormat,
+
+    /// Write the DAT to this file instead of stdout
+    #[clap(short, long)]
+    pub output: Option<String>,
+}
+
+#[derive(Clone, Debug, ValueEnum)]
+pub enum DatFormat {
+    /// No-Intro / Logiqx XML
+    Xml,
+    /// ClrMamePro text
+    Clrmamepro,
+}
+
+#[derive(Clone, Debug, ClapArgs)]
+pub struct DatDiffArgs {
+    /// The older version of the DAT
+    pub old: String,
+
+    /// The newer version of the DAT
+    pub new: String,
+
+    /// How output should be formatted
+    #[clap(long, default_value_t = Format::Text)]
+    #[arg(value_enum)]
+    pub format: Format,
+
+    /// Only show changes which involve files present in the collection
+    #[clap(long, default_value_t = false)]
+    pub affected: bool,
+}
+
+#[derive(Clone, Debug, ClapArgs)]
+pub struct DatCheckArgs {
+    /// The DAT to check
+    pub file: String,
+}
+
+#[derive(Clone, Debug, ClapArgs)]
+pub struct SyncNamesArgs {
+    /// The DAT which local files are currently named after
+    #[clap(long, required_unless_present = "undo")]
+    pub from: Option<String>,
+
+    /// The DAT which local files should be renamed to follow
+    #[clap(long, required_unless_present = "undo")]
+    pub to: Option<String>,
+
+    /// Also match files whose names are not recognized by their CRC
+    #[clap(long, default_value_t = false)]
+    pub by_hash: bool,
+
+    /// Print what would be renamed without changing anything
+    #[clap(long, default_value_t = false)]
+    pub dry_run: bool,
+
+    /// Where to record renames so that they can be undone later
+    #[clap(long, default_value = "romlint-sync-names.json")]
+    pub journal: String,
+
+    /// Revert the renames recorded in the given journal
+    #[clap(long, conflicts_with_all = ["from", "to"])]
+    pub undo: Option<String>,
+}
+
+#[derive(Clone, Debug, ClapArgs)]
+pub struct MissingArgs {
+    /// How output should be formatted
+    #[clap(long, default_value_t = Format::Text)]
+    #[arg(value_enum)]
+    pub format: Format,
+
+    /// Only print how complete each system is, without listing missing games
+    #[clap(long, default_value_t = false)]
+    pub summary: bool,
+}
+
+#[derive(Clone, Debug, ClapArgs)]
+pub struct DupesArgs {
+    /// How output should be formatted
+    #[clap(long, default_value_t = Format::Text)]
+    #[arg(value_enum)]
+    pub format: Format,
+
+    /// Find files holding the same game from a DAT, even in different containers like a .zip
+    /// and a .7z, instead of identical content
+    #[clap(long, default_value_t = false)]
+    pub by_game: bool,
+
+    /// Check every file, even those listed in .romlintignore files
+    #[clap(long, default_value_t = false)]
+    pub no_ignore: bool,
+}
+
+#[derive(Clone, Debug, ClapArgs)]
+pub struct M3uArgs {
+    /// Print which playlists would be written without writing them
+    #[clap(long, default_value_t = false)]
+    pub dry_run: bool,
+
+    /// Replace playlists which already exist
+    #[clap(long, default_value_t = false)]
+    pub force: bool,
+}
+
+#[derive(Clone, Debug, ValueEnum)]
+pub enum Format {
+    /// Plain text, one entry per line
+    Text,
+    /// JSON blob
+    Json,
+}
+
 #[derive(Clone, Debug, ValueEnum)]
 pub enum Reporter {
     /// Rich, color UI meant for interactive terminal sessions
     Ansi,
     /// JSON blob
     Json,
+    /// One JSON object per line, written as each file is linted
+    Ndjson,
+    /// SARIF 2.1.0 log, for code scanning tools
+    Sarif,
+    /// JUnit XML, with a test suite per system and a test case per file
+    Junit,
+    /// Self-contained HTML page, for sharing with people who don't use the CLI
+    Html,
 }
 
 #[derive(Clone, Debug, ClapArgs)]
@@ -62,4 +238,16 @@ pub struct LintArgs {
     #[clap(long, default_value_t = Reporter::Ansi)]
     #[arg(value_enum)]
     pub reporter: Reporter,
+
+    /// Hide failures which are recorded in the given baseline file, and fail only on new ones
+    #[clap(long)]
+    pub baseline: Option<String>,
+
+    /// Record every current failure in the given baseline file
+    #[clap(long)]
+    pub write_baseline: Option<String>,
+
+    /// Lint everything, even files and lints listed in .romlintignore files
+    #[clap(long, default_value_t = false)]
+    pub no_ignore: bool,
 }
@@ -0,0 +1,145 @@
+use crate::error::{BaselineErr, IoErr, Result};
+use crate::linter::Diagnostic;
+use serde::{Deserialize, Serialize};
+use snafu::prelude::*;
+use std::collections::{BTreeSet, HashSet};
+use std::fmt::{self, Display, Formatter};
+use std::fs;
+use std::path::Path;
+
+/// A diagnostic which is already known about, identified by the file it was reported for, the
+/// lint which reported it, and its message
+#[derive(Clone, Debug, Deserialize, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize)]
+pub struct Entry {
+    pub path: String,
+    pub lint: Option<String>,
+    pub message: String,
+}
+
+impl Entry {
+    fn new(path: &str, diagnostic: &Diagnostic) -> Self {
+        Self {
+            path: path.to_owned(),
+            lint: diagnostic.lint.clone(),
+            message: diagnostic.message.clone(),
+        }
+    }
+}
+
+impl Display for Entry {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match &self.lint {
+            Some(lint) => write!(f, "{}: [{lint}] {}", self.path, self.message),
+            None => write!(f, "{}: {}", self.path, self.message),
+        }
+    }
+}
+
+/// Failures which have been accepted for now, so that only new ones are reported. Every
+/// diagnostic seen during a run is recorded as well, so that a fresh baseline can be written and
+/// entries which no longer occur can be found.
+#[derive(Default)]
+pub struct Baseline {
+    known: HashSet<Entry>,
+    seen: BTreeSet<Entry>,
+    checked: HashSet<String>,
+    /// Files can be filtered more than once, as collection lints report after the file's own
+    /// lints, so failing files are counted by path
+    failing: HashSet<String>,
+}
+
+impl Baseline {
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let s = fs::read_to_string(path).context(IoErr { path })?;
+        let entries: Vec<Entry> = serde_json::from_str(&s).context(BaselineErr { path })?;
+
+        Ok(Self {
+            known: entries.into_iter().collect(),
+            ..Default::default()
+        })
+    }
+
+    /// Write every diagnostic seen so far, sorted so that the file diffs cleanly
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let path = path.as_ref();
+        let serialized = serde_json::to_string_pretty(&self.seen).unwrap();
+
+        fs::write(path, serialized + "\n").context(IoErr { path })
+    }
+
+    /// Record the diagnostics for a file, and return those which aren't in the baseline
+    pub fn filter(&mut self, path: &str, diagnostics: Vec<Diagnostic>) -> Vec<Diagnostic> {
+        self.checked.insert(path.to_owned());
+
+        let new = diagnostics
+            .into_iter()
+            .filter(|diagnostic| {
+                let entry = Entry::new(path, diagnostic);
+                let known = self.known.contains(&entry);
+                self.seen.insert(entry);
+                !known
+            })
+            .collect::<Vec<_>>();
+
+        if !new.is_empty() {
+            self.failing.insert(path.to_owned());
+        }
+
+        new
+    }
+
+    /// How many files had diagnostics which aren't in the baseline
+    pub fn failures(&self) -> usize {
+        self.failing.len()
+    }
+
+    /// Entries which didn't occur, and so can be removed from the baseline. Files which weren't
+    /// linted in this run only count if they no longer exist under `root`.
+    pub fn stale(&self, root: &Path) -> Vec<&Entry> {
+        let mut stale = self
+            .known
+            .iter()
+            .filter(|entry| !self.seen.contains(*entry))
+            .filter(|entry| self.checked.contains(&entry.path) || !root.join(&entry.path).exists())
+            .collect::<Vec<_>>();
+
+        stale.sort_unstable();
+        stale
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn diagnostic(lint: &str, message: &str) -> Diagnostic {
+        Diagnostic::new(Path::new("gb/Game.zip"), message).with_lint(lint)
+    }
+
+    #[test]
+    fn counts_each_failing_file_once() {
+        let mut baseline = Baseline::default();
+        baseline.filter("gb/Game.zip", vec![diagnostic("bad_dump", "bad dump")]);
+        baseline.filter(
+            "gb/Game.zip",
+            vec![diagnostic("duplicates", "same content")],
+        );
+        baseline.filter("gb/Other.zip", vec![]);
+
+        assert_eq!(baseline.failures(), 1);
+    }
+
+    #[test]
+    fn only_counts_new_failures() {
+        let mut baseline = Baseline::default();
+        baseline.known.insert(Entry::new(
+            "gb/Game.zip",
+            &diagnostic("bad_dump", "bad dump"),
+        ));
+
+        let new = baseline.filter("gb/Game.zip", vec![diagnostic("bad_dump", "bad dump")]);
+        assert!(new.is_empty());
+        assert_eq!(baseline.failures(), 0);
+    }
+}
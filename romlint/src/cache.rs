@@ -0,0 +1,133 @@
+use crate::checksum;
+use crate::db::{parse_dat, Dat};
+use crate::error::{CacheWriteErr, IoErr, Result};
+use serde::{Deserialize, Serialize};
+use snafu::prelude::*;
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter};
+use std::path::{Path, PathBuf};
+use tempfile::NamedTempFile;
+use tokio::task::spawn_blocking;
+
+/// The name of the cache directory, which sits next to the database directory
+pub const CACHE_DIR: &str = ".romlint-cache";
+
+/// Bump this whenever the layout of `Dat` changes so that stale caches are re-parsed
+const CACHE_VERSION: u32 = 5;
+
+/// Identifies the exact DAT contents a cache entry was built from
+#[derive(Debug, Deserialize, Eq, PartialEq, Serialize)]
+struct CacheKey {
+    version: u32,
+    crc: u32,
+    len: u64,
+}
+
+impl CacheKey {
+    fn for_file(path: &Path) -> io::Result<Self> {
+        let file = File::open(path)?;
+        let len = file.metadata()?.len();
+        let crc = checksum::crc32(BufReader::new(file))?;
+
+        Ok(Self {
+            version: CACHE_VERSION,
+            crc,
+            len,
+        })
+    }
+}
+
+/// A directory of precompiled databases. Each DAT gets one entry, named after the DAT and a hash
+/// of its full path, which is replaced whenever the contents of the DAT change.
+#[derive(Clone)]
+pub struct DatCache {
+    dir: PathBuf,
+}
+
+impl DatCache {
+    /// Create a cache which lives next to the given database directory
+    pub fn for_db_dir<P: AsRef<Path>>(db_dir: P) -> Self {
+        let db_dir = db_dir.as_ref();
+        let dir = db_dir.parent().unwrap_or(db_dir).join(CACHE_DIR);
+
+        Self { dir }
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Load the database at `path`, using a cached copy if one exists for the current contents
+    /// of the file. When the cache is missing or stale the DAT is parsed and the cache rewritten.
+    /// Hashing and parsing DATs is slow, so it's done on a blocking thread.
+    pub async fn load<P: Into<PathBuf>>(&self, path: P) -> Result<Dat> {
+        let cache = self.clone();
+        let path = path.into();
+
+        spawn_blocking(move || cache.load_blocking(&path))
+            .await
+            .expect("loading a database panicked")
+    }
+
+    fn load_blocking(&self, path: &Path) -> Result<Dat> {
+        let key = CacheKey::for_file(path).context(IoErr { path })?;
+        let cache_path = self.entry_path(path);
+
+        if let Some(dat) = read_entry(&cache_path, &key) {
+            log::debug!("using cached database for {}", path.display());
+            return Ok(dat);
+        }
+
+        let dat = Dat::new(parse_dat(path)?);
+
+        if let Err(err) = self.write_entry(&cache_path, &key, &dat) {
+            log::warn!("{err}");
+        }
+
+        Ok(dat)
+    }
+
+    /// DATs in different directories can share a name, so the name is followed by a hash of the
+    /// full path
+    fn entry_path(&self, path: &Path) -> PathBuf {
+        let full_path = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+        let hash = crc32fast::hash(full_path.as_os_str().as_encoded_bytes());
+
+        let mut name = path.file_name().unwrap_or_default().to_os_string();
+        name.push(format!("-{hash:08x}.bin"));
+        self.dir.join(name)
+    }
+
+    /// Entries are written to a temporary file which then replaces the old entry, so that an
+    /// interrupted write never leaves a truncated entry behind
+    fn write_entry(&self, cache_path: &Path, key: &CacheKey, dat: &Dat) -> Result<()> {
+        fs::create_dir_all(&self.dir).context(IoErr { path: &self.dir })?;
+
+        let file = NamedTempFile::new_in(&self.dir).context(IoErr { path: &self.dir })?;
+        let mut writer = BufWriter::new(file);
+        bincode::serialize_into(&mut writer, key).context(CacheWriteErr { path: cache_path })?;
+        bincode::serialize_into(&mut writer, dat).context(CacheWriteErr { path: cache_path })?;
+
+        let file = writer
+            .into_inner()
+            .map_err(|err| err.into_error())
+            .context(IoErr { path: cache_path })?;
+        file.persist(cache_path)
+            .map_err(|err| err.error)
+            .context(IoErr { path: cache_path })?;
+
+        Ok(())
+    }
+}
+
+fn read_entry(cache_path: &Path, key: &CacheKey) -> Option<Dat> {
+    let file = File::open(cache_path).ok()?;
+    let mut reader = BufReader::new(file);
+    let cached_key: CacheKey = bincode::deserialize_from(&mut reader).ok()?;
+
+    if &cached_key != key {
+        return None;
+    }
+
+    bincode::deserialize_from(&mut reader).ok()
+}
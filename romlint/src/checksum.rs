@@ -0,0 +1,67 @@
+use dat::Crc32;
+use md5::{Digest, Md5};
+use sha1::Sha1;
+use sha2::Sha256;
+use std::io::{Read, Result};
+
+const BUF_SIZE: usize = 64 * 1024;
+
+/// Every hash which a DAT can record for a ROM
+pub struct Checksums {
+    pub size: u64,
+    pub crc: Crc32,
+    pub md5: dat::Md5,
+    pub sha1: dat::Sha1,
+    pub sha256: dat::Sha256,
+}
+
+impl Checksums {
+    /// Hash everything remaining in `reader`. The input is only read once.
+    pub fn from_reader<R: Read>(mut reader: R) -> Result<Self> {
+        let mut size = 0;
+        let mut crc = crc32fast::Hasher::new();
+        let mut md5 = Md5::new();
+        let mut sha1 = Sha1::new();
+        let mut sha256 = Sha256::new();
+        let mut buf = vec![0; BUF_SIZE];
+
+        loop {
+            let len = reader.read(&mut buf)?;
+            if len == 0 {
+                break;
+            }
+
+            let chunk = &buf[..len];
+            size += len as u64;
+            crc.update(chunk);
+            md5.update(chunk);
+            sha1.update(chunk);
+            sha256.update(chunk);
+        }
+
+        Ok(Self {
+            size,
+            crc: Crc32::from(crc.finalize()),
+            md5: dat::Md5::from_bytes(md5.finalize().into()),
+            sha1: dat::Sha1::from_bytes(sha1.finalize().into()),
+            sha256: dat::Sha256::from_bytes(sha256.finalize().into()),
+        })
+    }
+}
+
+/// Compute only the CRC32 of everything remaining in `reader`
+pub fn crc32<R: Read>(mut reader: R) -> Result<u32> {
+    let mut hasher = crc32fast::Hasher::new();
+    let mut buf = vec![0; BUF_SIZE];
+
+    loop {
+        let len = reader.read(&mut buf)?;
+        if len == 0 {
+            break;
+        }
+
+        hasher.update(&buf[..len]);
+    }
+
+    Ok(hasher.finalize())
+}
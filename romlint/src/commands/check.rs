@@ -1,13 +1,16 @@
 use super::lint::LintContext;
 use crate::{
     error::{InvalidPathErr, Result},
-    filemeta::FileMeta,
+    filemeta::{read_listing, FileMeta},
     linter::Diagnostic,
-    scripts::exec_one,
+    scripts::{exec_dir, exec_one},
     ui::{Message, Report},
 };
 use snafu::OptionExt;
 
+/// The name diagnostics are reported under when a file can't be read
+pub const IO_LINT: &str = "io";
+
 pub fn check<F>(ctx: &LintContext, file: &FileMeta<'_>, send: F) -> Result<bool>
 where
     F: Fn(Message) -> Result<()>,
@@ -19,31 +22,75 @@ where
 
     send(Message::SetStatus(path.clone()))?;
 
-    let diagnostics = ctx.scripts().fold(Vec::new(), |mut acc, lint| {
+    let mut diagnostics = Vec::new();
+    for lint in ctx.scripts().filter(|lint| lint.lints_files()) {
+        if ctx.is_lint_ignored(file.path(), lint.name())? {
+            log::debug!("skipping {:?} for {:?}", lint, file.path());
+            continue;
+        }
+
         log::debug!("linting {:?} with {:?}", file.path(), lint);
         let result = exec_one(lint, file, ctx.databases());
         if let Err(err) = result {
-            let diag = create_diagnostic(file, err);
-            acc.push(diag);
+            let diag = create_diagnostic(file, err).with_lint(lint.name());
+            diagnostics.push(diag);
         }
+    }
 
-        acc
-    });
+    if file.metadata().is_dir() {
+        check_dir(ctx, file, &mut diagnostics)?;
+    }
 
+    let diagnostics = ctx.filter_baseline(&path, diagnostics);
     let passed = diagnostics.is_empty();
-    let report = Report { diagnostics, path };
+    let report = Report {
+        diagnostics,
+        path,
+        system: file.system().map(|s| s.to_owned()),
+    };
     send(Message::Report(report))?;
 
     Ok(passed)
 }
 
+/// Run the directory lints, which also get a listing of the directory's contents
+fn check_dir(ctx: &LintContext, dir: &FileMeta, diagnostics: &mut Vec<Diagnostic>) -> Result<()> {
+    if !ctx.scripts().any(|lint| lint.lints_dirs()) {
+        return Ok(());
+    }
+
+    let listing = match read_listing(dir.path()) {
+        Ok(listing) => listing,
+        Err(err) => {
+            diagnostics.push(Diagnostic::from_file(dir, err.to_string()).with_lint(IO_LINT));
+            return Ok(());
+        }
+    };
+
+    for lint in ctx.scripts().filter(|lint| lint.lints_dirs()) {
+        if ctx.is_lint_ignored(dir.path(), lint.name())? {
+            continue;
+        }
+
+        log::debug!("linting directory {:?} with {:?}", dir.path(), lint);
+        if let Err(err) = exec_dir(lint, dir, &listing, ctx.config()) {
+            diagnostics.push(create_diagnostic(dir, err).with_lint(lint.name()));
+        }
+    }
+
+    Ok(())
+}
+
 fn create_diagnostic(file: &FileMeta, err: mlua::Error) -> Diagnostic {
+    Diagnostic::from_file(file, error_message(err))
+}
+
+/// Errors raised by the lint API, like failed assertions, are wrapped by Lua
+pub fn error_message(err: mlua::Error) -> String {
     use mlua::Error::*;
 
-    let message = match err {
+    match err {
         CallbackError { cause, .. } => format!("{cause}"),
         err => format!("{err}"),
-    };
-
-    Diagnostic::from_file(file, message)
+    }
 }
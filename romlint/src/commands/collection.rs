@@ -0,0 +1,69 @@
+use super::check::error_message;
+use super::lint::LintContext;
+use crate::{
+    error::Result,
+    linter::Diagnostic,
+    scripts::{exec_collection, CollectionFile},
+    ui::{Message, Report},
+};
+use std::collections::{BTreeMap, HashSet};
+
+/// Run the collection lints for a single system, and send a report for each file they find
+/// problems with. Returns the paths of those files. A lint which fails outright is reported
+/// against the system's directory.
+pub fn check_collection<F>(
+    ctx: &LintContext,
+    system: &str,
+    files: &[CollectionFile],
+    send: F,
+) -> Result<HashSet<String>>
+where
+    F: Fn(Message) -> Result<()>,
+{
+    let root = ctx.cwd();
+    let mut diagnostics = BTreeMap::<String, Vec<Diagnostic>>::new();
+
+    for lint in ctx.scripts().filter(|lint| lint.lints_collection()) {
+        log::debug!("linting the {system} collection with {lint:?}");
+
+        let reports = match exec_collection(lint, system, files, ctx.config(), root) {
+            Ok(reports) => reports,
+            Err(err) => {
+                let diagnostic =
+                    Diagnostic::new(&root.join(system), error_message(err)).with_lint(lint.name());
+                diagnostics
+                    .entry(system.to_owned())
+                    .or_default()
+                    .push(diagnostic);
+                continue;
+            }
+        };
+
+        for (path, message) in reports {
+            let full_path = root.join(&path);
+            if ctx.is_lint_ignored(&full_path, lint.name())? {
+                continue;
+            }
+
+            let diagnostic = Diagnostic::new(&full_path, message).with_lint(lint.name());
+            diagnostics.entry(path).or_default().push(diagnostic);
+        }
+    }
+
+    let mut failed = HashSet::new();
+    for (path, diagnostics) in diagnostics {
+        let diagnostics = ctx.filter_baseline(&path, diagnostics);
+        if diagnostics.is_empty() {
+            continue;
+        }
+
+        send(Message::Report(Report {
+            diagnostics,
+            path: path.clone(),
+            system: Some(system.to_owned()),
+        }))?;
+        failed.insert(path);
+    }
+
+    Ok(failed)
+}
@@ -0,0 +1,28 @@
+use crate::{
+    args::{Args, DatCheckArgs},
+    error::{DatabaseReadErr, InvalidDatabaseErr, IoErr, Result},
+};
+use snafu::prelude::*;
+use std::{fs::File, io::BufReader};
+
+/// Validate a DAT, printing each problem with its location in the file
+pub fn dat_check(args: &Args, check_args: &DatCheckArgs) -> Result<()> {
+    let path = args.cwd().join(&check_args.file);
+    let file = File::open(&path).context(IoErr { path: &path })?;
+    let issues = dat::validate(BufReader::new(file)).context(DatabaseReadErr { path: &path })?;
+
+    for issue in &issues {
+        println!("{}:{issue}", path.display());
+    }
+
+    ensure!(
+        issues.is_empty(),
+        InvalidDatabaseErr {
+            path,
+            count: issues.len()
+        }
+    );
+
+    println!("{} is valid", path.display());
+    Ok(())
+}
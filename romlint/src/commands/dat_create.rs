@@ -0,0 +1,159 @@
+use crate::{
+    args::{Args, DatCreateArgs, DatFormat},
+    cache::CACHE_DIR,
+    checksum::Checksums,
+    error::{IoErr, Result, WalkErr},
+    ignore::IGNORE_FILE,
+};
+use dat::{ClrMamePro, DataFile, Game, Header, Rom};
+use dir_walker::WalkOptions;
+use futures::TryStreamExt;
+use snafu::prelude::*;
+use std::{
+    fs::File,
+    io::{self, BufReader, BufWriter, Write},
+    path::Path,
+};
+use zip::ZipArchive;
+
+/// Files which sit alongside ROMs, but aren't ROMs themselves
+const NON_ROM_EXTENSIONS: [&str; 3] = ["m3u", "nfo", "txt"];
+
+/// Hash every file in a directory and write a DAT describing them. Each zip archive is a game
+/// named after the archive, with the files inside of it as its ROMs, since that is what DATs
+/// conventionally record.
+pub async fn dat_create(args: &Args, create_args: &DatCreateArgs) -> Result<()> {
+    let dir = args.cwd().join(&create_args.dir);
+    let mut files = WalkOptions::new()
+        .filter(|path, _| path.file_name().is_none_or(|name| name != CACHE_DIR))
+        .walk(&dir)
+        .await
+        .context(IoErr { path: &dir })?
+        .try_filter(|file| futures::future::ready(file.meta.is_file() && is_rom(&file.path)))
+        .try_collect::<Vec<_>>()
+        .await
+        .context(WalkErr)?;
+
+    files.sort_unstable_by(|a, b| a.path.cmp(&b.path));
+
+    let mut games = Vec::with_capacity(files.len());
+    for file in files {
+        let path = file.path.as_path();
+        let is_zip = path.extension().and_then(|ext| ext.to_str()) == Some("zip");
+        let file_name = path.file_name().and_then(|s| s.to_str()).unwrap_or("");
+
+        if is_zip {
+            let roms = zip_roms(path).context(IoErr { path })?;
+            games.push(game(file_name, roms));
+        } else {
+            let reader = BufReader::new(File::open(path).context(IoErr { path })?);
+            let checksums = Checksums::from_reader(reader).context(IoErr { path })?;
+            games.push(game(file_name, vec![rom(file_name, checksums)]));
+        }
+    }
+
+    games.sort_by(|a, b| a.name.cmp(&b.name));
+
+    let name = create_args.name.clone().unwrap_or_else(|| {
+        dir.file_name()
+            .map(|s| s.to_string_lossy().into_owned())
+            .unwrap_or_default()
+    });
+    let description = create_args.description.clone().unwrap_or(name.clone());
+    let datafile = DataFile {
+        header: Header {
+            id: 0,
+            name,
+            description,
+            version: create_args.version.clone(),
+            author: create_args.author.clone(),
+            homepage: String::new(),
+            url: String::new(),
+            subset: None,
+            clrmamepro: ClrMamePro {
+                force_no_dump: None,
+            },
+        },
+        games,
+    };
+
+    match &create_args.output {
+        Some(output) => {
+            let path = args.cwd().join(output);
+            let file = File::create(&path).context(IoErr { path: &path })?;
+            let mut writer = BufWriter::new(file);
+            write_dat(&datafile, &create_args.format, &mut writer).context(IoErr { path: &path })
+        }
+        None => write_dat(&datafile, &create_args.format, &mut io::stdout().lock())
+            .context(IoErr { path: "stdout" }),
+    }
+}
+
+fn write_dat<W: Write>(datafile: &DataFile, format: &DatFormat, w: &mut W) -> io::Result<()> {
+    match format {
+        DatFormat::Xml => datafile.write_xml(w)?,
+        DatFormat::Clrmamepro => datafile.write_clrmamepro(w)?,
+    }
+
+    w.flush()
+}
+
+fn is_rom(path: &Path) -> bool {
+    if path.file_name().is_some_and(|name| name == IGNORE_FILE) {
+        return false;
+    }
+
+    let extension = path.extension().and_then(|ext| ext.to_str());
+    !extension.is_some_and(|ext| {
+        NON_ROM_EXTENSIONS
+            .iter()
+            .any(|skipped| ext.eq_ignore_ascii_case(skipped))
+    })
+}
+
+fn zip_roms(path: &Path) -> io::Result<Vec<Rom>> {
+    let mut zip = ZipArchive::new(BufReader::new(File::open(path)?))?;
+    let mut roms = Vec::with_capacity(zip.len());
+
+    for i in 0..zip.len() {
+        let file = zip.by_index(i)?;
+        if file.is_dir() {
+            continue;
+        }
+
+        let name = file.name().to_owned();
+        let checksums = Checksums::from_reader(file)?;
+        roms.push(rom(&name, checksums));
+    }
+
+    Ok(roms)
+}
+
+/// A game named after the file it was read from
+fn game(file_name: &str, roms: Vec<Rom>) -> Game {
+    let name = Path::new(file_name)
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or(file_name)
+        .to_owned();
+
+    Game {
+        description: name.clone(),
+        name,
+        roms,
+    }
+}
+
+fn rom(file_name: &str, checksums: Checksums) -> Rom {
+    Rom {
+        name: file_name.to_owned(),
+        size: checksums.size as usize,
+        crc: Some(checksums.crc),
+        md5: Some(checksums.md5),
+        sha1: Some(checksums.sha1),
+        sha256: Some(checksums.sha256),
+        status: None,
+        serial: None,
+        header: None,
+    }
+}
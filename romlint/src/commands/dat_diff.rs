@@ -0,0 +1,113 @@
+use super::walk::collection_entries;
+use crate::{
+    args::{Args, DatDiffArgs, Format},
+    config::Config,
+    db::read_dat,
+    error::Result,
+};
+use dat::{diff, Change, Diff, Game};
+use serde::Serialize;
+use std::collections::HashSet;
+
+#[derive(Serialize)]
+struct DiffReport<'a> {
+    added: Vec<&'a str>,
+    removed: Vec<&'a str>,
+    renamed: Vec<Rename<'a>>,
+    redumped: Vec<&'a str>,
+}
+
+#[derive(Serialize)]
+struct Rename<'a> {
+    from: &'a str,
+    to: &'a str,
+}
+
+/// Print the games which were added, removed, renamed or re-dumped between two versions of a DAT
+pub async fn dat_diff(args: &Args, diff_args: &DatDiffArgs) -> Result<()> {
+    let cwd = args.cwd();
+    let old = read_dat(cwd.join(&diff_args.old)).await?;
+    let new = read_dat(cwd.join(&diff_args.new)).await?;
+    let mut diff = diff(&old, &new);
+
+    if diff_args.affected {
+        let local = local_stems(args).await?;
+        retain_affected(&mut diff, &local);
+    }
+
+    let report = DiffReport {
+        added: names(&diff.added),
+        removed: names(&diff.removed),
+        renamed: diff
+            .renamed
+            .iter()
+            .map(|change| Rename {
+                from: change.old.name.as_str(),
+                to: change.new.name.as_str(),
+            })
+            .collect(),
+        redumped: diff
+            .redumped
+            .iter()
+            .map(|change| change.new.name.as_str())
+            .collect(),
+    };
+
+    match diff_args.format {
+        Format::Text => print_text(&report),
+        Format::Json => {
+            let serialized = serde_json::to_string(&report).unwrap();
+            println!("{serialized}");
+        }
+    }
+
+    Ok(())
+}
+
+/// The names of every file in the collection which `lint` would scan, without extensions
+async fn local_stems(args: &Args) -> Result<HashSet<String>> {
+    let config = Config::from_path(args.config_path()).await?;
+    let stems = collection_entries(args, &config, false)
+        .await?
+        .iter()
+        .filter_map(|entry| entry.path.file_stem()?.to_str().map(|s| s.to_owned()))
+        .collect();
+
+    Ok(stems)
+}
+
+fn retain_affected(diff: &mut Diff, local: &HashSet<String>) {
+    let has = |game: &Game| local.contains(game.name.as_str());
+    let change_has = |change: &Change| has(change.old) || has(change.new);
+
+    diff.added.retain(|game| has(game));
+    diff.removed.retain(|game| has(game));
+    diff.renamed.retain(change_has);
+    diff.redumped.retain(change_has);
+}
+
+fn names<'a>(games: &[&'a Game]) -> Vec<&'a str> {
+    games.iter().map(|game| game.name.as_str()).collect()
+}
+
+fn print_text(report: &DiffReport) {
+    println!("Added ({})", report.added.len());
+    for name in &report.added {
+        println!("  + {name}");
+    }
+
+    println!("Removed ({})", report.removed.len());
+    for name in &report.removed {
+        println!("  - {name}");
+    }
+
+    println!("Renamed ({})", report.renamed.len());
+    for rename in &report.renamed {
+        println!("  ~ {} -> {}", rename.from, rename.to);
+    }
+
+    println!("Re-dumped ({})", report.redumped.len());
+    for name in &report.redumped {
+        println!("  * {name}");
+    }
+}
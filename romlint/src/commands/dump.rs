@@ -1,28 +1,35 @@
-use crate::{args::Args, config::Config, db, error::Result, ui::Message};
+use crate::{
+    args::{Args, DumpArgs},
+    config::Config,
+    db,
+    error::Result,
+    ui,
+};
 
-/// Dump all known ROM names to stdout. Each name is printed on a separate line.
-pub async fn dump(args: Args) -> Result<()> {
+/// Dump all known ROM names to stdout. Each name is printed on a separate line, optionally
+/// followed by the name of the DAT it was found in.
+pub async fn dump(args: &Args, dump_args: &DumpArgs) -> Result<()> {
     let config = Config::from_path(args.config_path()).await?;
     let db_path = args.cwd().join(config.db_dir());
     let dbs;
 
-    if let Some(sys) = args.system {
-        dbs = db::load_only(&db_path, &[&sys], &nop).await?;
+    if let Some(sys) = &args.system {
+        dbs = db::load_only(&db_path, &config, &[sys], &ui::nop).await?;
 
         if dbs.is_empty() {
             eprint!("Unable to find a database for the system '{sys}'.");
         }
     } else {
-        dbs = db::load_all(&db_path, &nop).await?;
+        dbs = db::load_all(&db_path, &config, &ui::nop).await?;
     }
 
-    dbs.iter()
-        .flat_map(|db| db.files())
-        .for_each(|file| println!("{}", file.name));
-
-    Ok(())
-}
+    dbs.iter().flat_map(|db| db.files()).for_each(|file| {
+        if dump_args.show_source {
+            println!("{}\t{}", file.game.name, file.source_name());
+        } else {
+            println!("{}", file.game.name);
+        }
+    });
 
-fn nop(_message: Message) -> Result<()> {
     Ok(())
 }
@@ -0,0 +1,120 @@
+use super::walk::collection_entries;
+use crate::{
+    args::{Args, DupesArgs, Format},
+    config::Config,
+    db::{self, Databases},
+    duplicates::{self, ContentGroup, GameGroup, ScannedFile},
+    error::Result,
+    ui,
+};
+use std::path::Path;
+
+/// Print the content which is stored more than once in the collection, or with `--by-game`,
+/// the games which are stored in more than one file
+pub async fn dupes(args: &Args, dupes_args: &DupesArgs) -> Result<()> {
+    let config = Config::from_path(args.config_path()).await?;
+    let cwd = args.cwd();
+    let entries = collection_entries(args, &config, dupes_args.no_ignore).await?;
+
+    let mut files = Vec::new();
+    for entry in entries.iter().filter(|entry| entry.meta.is_file()) {
+        let relative = entry.path.strip_prefix(&cwd).unwrap_or(&entry.path);
+        let system = args.system.clone().or_else(|| system_of(relative, &config));
+        let relative = relative.to_string_lossy().into_owned();
+
+        match ScannedFile::read(&entry.path, relative, system) {
+            Ok(file) => files.push(file),
+            Err(err) => log::warn!("skipping {}: {err}", entry.path.display()),
+        }
+    }
+
+    if dupes_args.by_game {
+        let db_path = cwd.join(config.db_dir());
+        let databases = match &args.system {
+            Some(sys) => db::load_only(&db_path, &config, &[sys.as_str()], &ui::nop).await?,
+            None => db::load_all(&db_path, &config, &ui::nop).await?,
+        };
+
+        print_games(&files, &databases, &dupes_args.format);
+    } else {
+        print_contents(&duplicates::by_content(&files), &dupes_args.format);
+    }
+
+    Ok(())
+}
+
+/// Files directly inside a configured system's directory belong to that system
+fn system_of(relative: &Path, config: &Config) -> Option<String> {
+    let mut components = relative.iter();
+    let system = components.next()?.to_str()?;
+    let in_system_dir = components.next().is_some() && components.next().is_none();
+
+    (in_system_dir && config.systems().any(|s| s == system)).then(|| system.to_owned())
+}
+
+fn print_contents(groups: &[ContentGroup], format: &Format) {
+    match format {
+        Format::Text => {
+            for group in groups {
+                println!("{} bytes, crc {}:", group.size, group.crc);
+
+                for location in &group.locations {
+                    match location.member {
+                        Some(member) => println!("  {}: {member}", location.path),
+                        None => println!("  {}", location.path),
+                    }
+                }
+            }
+        }
+        Format::Json => {
+            let serialized = serde_json::to_string(groups).unwrap();
+            println!("{serialized}");
+        }
+    }
+}
+
+fn print_games(files: &[ScannedFile], databases: &Databases, format: &Format) {
+    let groups: Vec<GameGroup> = duplicates::by_game(files, databases);
+
+    match format {
+        Format::Text => {
+            for group in &groups {
+                println!("{}: {}", group.system, group.game);
+
+                for path in &group.paths {
+                    println!("  {path}");
+                }
+            }
+        }
+        Format::Json => {
+            let serialized = serde_json::to_string(&groups).unwrap();
+            println!("{serialized}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn only_files_directly_inside_a_system_belong_to_it() {
+        let config: Config = toml::from_str(
+            r#"
+            [global]
+            db_dir = "dats"
+
+            [system.gb]
+            archive_format = "zip"
+            raw_format = "gb"
+            "#,
+        )
+        .unwrap();
+
+        let system = |path: &str| system_of(Path::new(path), &config);
+        assert_eq!(system("gb/Game.gb").as_deref(), Some("gb"));
+        assert_eq!(system("gb/extras/Game.gb"), None);
+        assert_eq!(system("gb"), None);
+        assert_eq!(system("nes/Game.nes"), None);
+    }
+}
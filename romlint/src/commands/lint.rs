@@ -1,12 +1,19 @@
 use crate::args::{Args, LintArgs, Reporter};
+use crate::baseline::Baseline;
 use crate::commands::{check, scan};
 use crate::config::Config;
 use crate::db::{self, Databases};
-use crate::error::{BrokenPipeErr, IoErr, Result};
+use crate::error::{BrokenPipeErr, IoErr, NewFailuresErr, Result};
 use crate::filemeta::{Extractor, FileMeta, ZipExtractor};
+use crate::ignore::Ignores;
+use crate::linter::Diagnostic;
 use crate::scripts::{Requirements, Script, ScriptLoader};
-use crate::ui::{AnsiReporter, JsonReporter, Message, Summary, Ui};
-use snafu::ResultExt;
+use crate::ui::{
+    AnsiReporter, HtmlReporter, JsonReporter, JunitReporter, Message, NdjsonReporter,
+    SarifReporter, Summary, Ui,
+};
+use snafu::prelude::*;
+use std::cell::RefCell;
 use std::collections::HashMap;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
@@ -15,9 +22,11 @@ use std::{sync::mpsc, thread::spawn};
 use tokio::fs::read_dir;
 
 pub struct LintContext {
+    baseline: Option<RefCell<Baseline>>,
     config: Config,
     cwd: PathBuf,
     databases: Arc<Databases>,
+    ignores: Option<RefCell<Ignores>>,
     scripts: ScriptLoader,
     system: Option<String>,
 }
@@ -32,16 +41,54 @@ impl LintContext {
     ) -> Self {
         let system = system.cloned();
         let databases = Arc::new(databases);
+        let ignores = Some(RefCell::new(Ignores::new(&cwd)));
 
         Self {
+            baseline: None,
             config,
             cwd,
             databases,
+            ignores,
             scripts,
             system,
         }
     }
 
+    pub fn with_baseline(mut self, baseline: Baseline) -> Self {
+        self.baseline = Some(RefCell::new(baseline));
+        self
+    }
+
+    /// Drop diagnostics which are in the baseline, if there is one
+    pub fn filter_baseline(&self, path: &str, diagnostics: Vec<Diagnostic>) -> Vec<Diagnostic> {
+        match &self.baseline {
+            Some(baseline) => baseline.borrow_mut().filter(path, diagnostics),
+            None => diagnostics,
+        }
+    }
+
+    /// Lint every file, regardless of any ignore files
+    pub fn without_ignores(mut self) -> Self {
+        self.ignores = None;
+        self
+    }
+
+    /// Whether an ignore file says to skip the file entirely
+    pub fn is_ignored(&self, path: &Path) -> Result<bool> {
+        match &self.ignores {
+            Some(ignores) => ignores.borrow_mut().is_ignored(path),
+            None => Ok(false),
+        }
+    }
+
+    /// Whether an ignore file says to skip a lint for the file
+    pub fn is_lint_ignored(&self, path: &Path, lint: &str) -> Result<bool> {
+        match &self.ignores {
+            Some(ignores) => ignores.borrow_mut().is_lint_ignored(path, lint),
+            None => Ok(false),
+        }
+    }
+
     pub fn relative_path<P: AsRef<Path>>(&self, path: P) -> Option<PathBuf> {
         let path = path.as_ref();
         path.strip_prefix(self.cwd.as_path())
@@ -65,6 +112,10 @@ impl LintContext {
         self.system.as_ref()
     }
 
+    pub fn cwd(&self) -> &Path {
+        &self.cwd
+    }
+
     pub fn scan_dirs(&self) -> PathBuf {
         match &self.system {
             Some(system) => self.cwd.join(system),
@@ -96,20 +147,27 @@ pub async fn lint(args: &Args, lint_args: &LintArgs) -> Result<()> {
     let reporter: Box<dyn crate::ui::Reporter + Send + Sync> = match lint_args.reporter {
         Reporter::Ansi => Box::new(AnsiReporter::new(!hide_passes)),
         Reporter::Json => Box::new(JsonReporter::new()),
+        Reporter::Ndjson => Box::new(NdjsonReporter),
+        Reporter::Sarif => {
+            let rules = script_loader.iter().map(Into::into).collect();
+            Box::new(SarifReporter::new(rules))
+        }
+        Reporter::Junit => Box::new(JunitReporter::new()),
+        Reporter::Html => Box::new(HtmlReporter::new()),
     };
     let ui_thread = spawn(move || Ui::new(rx, reporter).run());
     let on_message = |message: Message| tx.send(message).context(BrokenPipeErr);
 
     let db_path = args.cwd().join(config.db_dir());
     let databases = if let Some(sys) = &args.system {
-        db::load_only(&db_path, &[sys.as_str()], &on_message)
+        db::load_only(&db_path, &config, &[sys.as_str()], &on_message)
             .await
             .unwrap()
     } else {
-        db::load_all(&db_path, &on_message).await.unwrap()
+        db::load_all(&db_path, &config, &on_message).await.unwrap()
     };
 
-    let ctx = LintContext::new(
+    let mut ctx = LintContext::new(
         args.cwd(),
         args.system.as_ref(),
         databases,
@@ -117,6 +175,16 @@ pub async fn lint(args: &Args, lint_args: &LintArgs) -> Result<()> {
         script_loader,
     );
 
+    if lint_args.no_ignore {
+        ctx = ctx.without_ignores();
+    }
+
+    if let Some(path) = &lint_args.baseline {
+        ctx = ctx.with_baseline(Baseline::load(args.cwd().join(path))?);
+    } else if lint_args.write_baseline.is_some() {
+        ctx = ctx.with_baseline(Baseline::default());
+    }
+
     if let Some(file) = lint_args.file.as_ref() {
         let start_time = Instant::now();
         let mut extractors = HashMap::<String, Box<dyn Extractor>>::new();
@@ -149,5 +217,33 @@ pub async fn lint(args: &Args, lint_args: &LintArgs) -> Result<()> {
 
     ui_thread.join().unwrap()?;
 
+    match ctx.baseline {
+        Some(baseline) => finish_baseline(&args.cwd(), lint_args, &baseline.into_inner()),
+        None => Ok(()),
+    }
+}
+
+/// Write out the new baseline if one was requested, and point out entries in the old baseline
+/// which can be removed
+fn finish_baseline(cwd: &Path, lint_args: &LintArgs, baseline: &Baseline) -> Result<()> {
+    if let Some(path) = &lint_args.write_baseline {
+        baseline.save(cwd.join(path))?;
+    }
+
+    if lint_args.baseline.is_none() {
+        return Ok(());
+    }
+
+    let stale = baseline.stale(cwd);
+    if !stale.is_empty() {
+        eprintln!("{} baseline entries no longer occur:", stale.len());
+        for entry in stale {
+            eprintln!("  {entry}");
+        }
+    }
+
+    let count = baseline.failures();
+    ensure!(count == 0, NewFailuresErr { count });
+
     Ok(())
 }
@@ -0,0 +1,151 @@
+use crate::{
+    args::{Args, M3uArgs},
+    config::Config,
+    error::{IoErr, Result, WalkErr},
+};
+use dir_walker::WalkOptions;
+use futures::TryStreamExt;
+use snafu::prelude::*;
+use std::{collections::BTreeMap, fs, path::Path};
+
+/// Disc images which playlists point at, from most to least preferred when a disc is stored in
+/// more than one format
+const DISC_FORMATS: [&str; 2] = ["chd", "cue"];
+
+/// Write a `.m3u` playlist for each multi-disc game in each system's directory, listing its discs
+/// in order. Existing playlists are left alone unless `--force` is given.
+pub async fn m3u(args: &Args, m3u_args: &M3uArgs) -> Result<()> {
+    let config = Config::from_path(args.config_path()).await?;
+    let systems = match &args.system {
+        Some(system) => vec![system.as_str()],
+        None => config.systems().collect(),
+    };
+
+    for system in systems {
+        let dir = args.cwd().join(system);
+        if !dir.exists() {
+            continue;
+        }
+
+        for (set, discs) in disc_sets(&dir).await? {
+            let path = dir.join(format!("{set}.m3u"));
+            if path.exists() && !m3u_args.force {
+                log::info!("not overwriting existing playlist {}", path.display());
+                continue;
+            }
+
+            println!("{}", path.display());
+
+            if !m3u_args.dry_run {
+                let contents = discs
+                    .into_values()
+                    .map(|disc| disc + "\n")
+                    .collect::<String>();
+                fs::write(&path, contents).context(IoErr { path: &path })?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// The discs of each multi-disc game directly inside `dir`, by set name and disc number. Sets of
+/// a single disc don't need a playlist, so they're left out.
+async fn disc_sets(dir: &Path) -> Result<BTreeMap<String, BTreeMap<u32, String>>> {
+    let files = WalkOptions::new()
+        .max_depth(0)
+        .hidden(false)
+        .sorted(true)
+        .walk(dir)
+        .await
+        .context(IoErr { path: dir })?
+        .try_filter(|file| futures::future::ready(file.meta.is_file()))
+        .try_collect::<Vec<_>>()
+        .await
+        .context(WalkErr)?;
+
+    let mut sets = BTreeMap::<String, BTreeMap<u32, (usize, String)>>::new();
+    for file in files {
+        let Some((name, rank)) = disc_file(&file.path) else {
+            continue;
+        };
+        let stem = file.path.file_stem().and_then(|s| s.to_str());
+        let Some((set, number)) = stem.and_then(disc_number) else {
+            continue;
+        };
+
+        let discs = sets.entry(set).or_default();
+        if discs
+            .get(&number)
+            .is_none_or(|(existing, _)| rank < *existing)
+        {
+            discs.insert(number, (rank, name));
+        }
+    }
+
+    Ok(sets
+        .into_iter()
+        .filter(|(_, discs)| discs.len() > 1)
+        .map(|(set, discs)| {
+            let discs = discs.into_iter().map(|(n, (_, name))| (n, name)).collect();
+            (set, discs)
+        })
+        .collect())
+}
+
+/// The file name of a disc image, along with how preferred its format is
+fn disc_file(path: &Path) -> Option<(String, usize)> {
+    let ext = path.extension()?.to_str()?.to_lowercase();
+    let rank = DISC_FORMATS.iter().position(|format| *format == ext)?;
+    let name = path.file_name()?.to_str()?.to_owned();
+
+    Some((name, rank))
+}
+
+/// Split the `(Disc N)` tag out of the name of one disc of a multi-disc game, giving the name of
+/// the whole set and the disc's number. For example, "Game (USA) (Disc 2)" is disc 2 of the set
+/// "Game (USA)".
+fn disc_number(name: &str) -> Option<(String, u32)> {
+    let start = name.find("(Disc ")?;
+    let len = name[start..].find(')')? + 1;
+    let number = name[start + "(Disc ".len()..start + len - 1].parse().ok()?;
+    let set = format!("{}{}", name[..start].trim_end(), &name[start + len..]);
+
+    Some((set, number))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn splits_disc_tags() {
+        assert_eq!(
+            disc_number("Game (USA) (Disc 2)"),
+            Some(("Game (USA)".to_owned(), 2))
+        );
+    }
+
+    #[test]
+    fn reads_multi_digit_disc_numbers() {
+        assert_eq!(
+            disc_number("Game (Japan) (Disc 10)"),
+            Some(("Game (Japan)".to_owned(), 10))
+        );
+    }
+
+    #[test]
+    fn keeps_tags_after_the_disc_tag() {
+        assert_eq!(
+            disc_number("Game (Europe) (Disc 1) (Rev 1)"),
+            Some(("Game (Europe) (Rev 1)".to_owned(), 1))
+        );
+    }
+
+    #[test]
+    fn skips_names_without_a_disc_number() {
+        assert_eq!(disc_number("Game (USA)"), None);
+        assert_eq!(disc_number("Game (USA) (Disc A)"), None);
+        assert_eq!(disc_number("Game (USA) (Disc 1"), None);
+    }
+}
@@ -0,0 +1,128 @@
+use crate::{
+    args::{Args, Format, MissingArgs},
+    config::Config,
+    db::{self, Database},
+    error::{IoErr, Result, WalkErr},
+    ui,
+};
+use dir_walker::walk;
+use futures::TryStreamExt;
+use serde::Serialize;
+use snafu::prelude::*;
+use std::{collections::HashSet, path::Path};
+
+/// How much of a single system's DATs is present in the collection
+#[derive(Serialize)]
+struct Completeness<'a> {
+    system: &'a str,
+    have: usize,
+    total: usize,
+    /// Games which were never dumped, and so aren't counted in `total`
+    nodump: usize,
+    missing: Vec<&'a str>,
+}
+
+impl<'a> Completeness<'a> {
+    fn new(system: &'a str, db: &'a Database, local: &HashSet<String>) -> Self {
+        let mut completeness = Self {
+            system,
+            have: 0,
+            total: 0,
+            nodump: 0,
+            missing: Vec::new(),
+        };
+
+        for entry in db.files() {
+            if !entry.is_collectable() {
+                completeness.nodump += 1;
+            } else if local.contains(entry.game.name.as_str()) {
+                completeness.have += 1;
+                completeness.total += 1;
+            } else {
+                completeness.missing.push(&entry.game.name);
+                completeness.total += 1;
+            }
+        }
+
+        completeness
+    }
+
+    fn percent(&self) -> f64 {
+        if self.total == 0 {
+            return 100.0;
+        }
+
+        self.have as f64 / self.total as f64 * 100.0
+    }
+}
+
+/// Print which games from each system's DATs are missing, along with how complete each system
+/// is. Games which were never dumped are left out, unless the DAT requires them.
+pub async fn missing(args: &Args, missing_args: &MissingArgs) -> Result<()> {
+    let config = Config::from_path(args.config_path()).await?;
+    let db_path = args.cwd().join(config.db_dir());
+    let dbs = match &args.system {
+        Some(sys) => db::load_only(&db_path, &config, &[sys.as_str()], &ui::nop).await?,
+        None => db::load_all(&db_path, &config, &ui::nop).await?,
+    };
+
+    let mut reports = Vec::new();
+    for (system, db) in dbs.systems() {
+        let local = local_stems(&args.cwd().join(system)).await?;
+        let mut report = Completeness::new(system, db, &local);
+
+        if missing_args.summary {
+            report.missing.clear();
+        }
+
+        reports.push(report);
+    }
+
+    match missing_args.format {
+        Format::Text => reports.iter().for_each(print_text),
+        Format::Json => {
+            let serialized = serde_json::to_string(&reports).unwrap();
+            println!("{serialized}");
+        }
+    }
+
+    Ok(())
+}
+
+async fn local_stems(dir: &Path) -> Result<HashSet<String>> {
+    if !dir.exists() {
+        return Ok(HashSet::new());
+    }
+
+    let stems = walk(dir)
+        .await
+        .context(IoErr { path: dir })?
+        .try_filter_map(|file| async move {
+            let stem = file
+                .path
+                .file_stem()
+                .and_then(|s| s.to_str())
+                .map(|s| s.to_owned());
+            Ok(stem)
+        })
+        .try_collect()
+        .await
+        .context(WalkErr)?;
+
+    Ok(stems)
+}
+
+fn print_text(report: &Completeness) {
+    println!(
+        "{}: {}/{} ({:.1}%), {} not dumped",
+        report.system,
+        report.have,
+        report.total,
+        report.percent(),
+        report.nodump
+    );
+
+    for name in &report.missing {
+        println!("  - {name}");
+    }
+}
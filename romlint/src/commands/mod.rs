@@ -1,9 +1,25 @@
 mod check;
+mod collection;
+mod dat_check;
+mod dat_create;
+mod dat_diff;
 mod dump;
+mod dupes;
 mod lint;
+mod m3u;
+mod missing;
 mod scan;
+mod sync_names;
+mod walk;
 
 pub use check::check;
+pub use dat_check::dat_check;
+pub use dat_create::dat_create;
+pub use dat_diff::dat_diff;
 pub use dump::dump;
+pub use dupes::dupes;
 pub use lint::lint;
+pub use m3u::m3u;
+pub use missing::missing;
 pub use scan::scan;
+pub use sync_names::sync_names;
@@ -1,13 +1,22 @@
 use super::check;
+use super::check::IO_LINT;
+use super::collection::check_collection;
 use super::lint::LintContext;
-use crate::error::IoErr;
+use crate::db::Database;
+use crate::error::{InvalidPathErr, IoErr};
 use crate::filemeta::{Extractor, FileMeta, ZipExtractor};
-use crate::ui::{Message, Summary};
+use crate::filter::PathFilter;
+use crate::ignore::IGNORE_FILE;
+use crate::linter::Diagnostic;
+use crate::scripts::CollectionFile;
+use crate::ui::{Collection, Message, Report, Summary};
 use crate::Result;
-use dir_walker::walk;
-use futures::TryStreamExt;
+use dir_walker::WalkOptions;
+use futures::StreamExt;
 use snafu::prelude::*;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::io;
+use std::path::Path;
 use std::time::Instant;
 
 pub async fn scan<F>(ctx: &LintContext, send: F) -> Result<()>
@@ -16,36 +25,157 @@ where
 {
     let mut summary = Summary::new(Instant::now());
     let mut extractors = HashMap::<String, Box<dyn Extractor>>::new();
+    let mut stems = HashMap::<String, HashSet<String>>::new();
+    let mut collections = BTreeMap::<String, Vec<CollectionFile>>::new();
+    let mut passed = HashSet::<String>::new();
+    let lints_collection = ctx.scripts().any(|lint| lint.lints_collection());
     let path = ctx.scan_dirs();
     let path = path.as_path();
+    let filter = PathFilter::new(ctx.cwd(), ctx.config())?;
 
     if ctx.should_read_archives() {
         extractors.insert("zip".to_string(), Box::new(ZipExtractor));
     }
 
     let mut stream = Box::pin(
-        walk(path)
+        WalkOptions::new()
+            .sorted(true)
+            .filter(move |path, is_dir| filter.allows(path, is_dir))
+            .walk(path)
             .await
-            .context(IoErr { path })?
-            .and_then(|file| async {
-                let config = ctx.config();
-                let system = ctx.system().map(|s| s.as_str());
-                FileMeta::from_dir_walker(file, system, config, &extractors).await
-            }),
+            .context(IoErr { path })?,
     );
 
-    while let Some(file) = stream.try_next().await.context(IoErr { path })? {
+    while let Some(entry) = stream.next().await {
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(err) => {
+                if !ctx.is_ignored(&err.path)? {
+                    let pass = report_io_error(ctx, &err.path, &err.source, &send)?;
+                    record(&mut summary, ctx.system().map_or("unknown", |s| s), pass);
+                }
+
+                continue;
+            }
+        };
+
+        let is_ignore_file = entry
+            .path
+            .file_name()
+            .is_some_and(|name| name == IGNORE_FILE);
+        if is_ignore_file || ctx.is_ignored(&entry.path)? {
+            continue;
+        }
+
+        let entry_path = entry.path.clone();
+        let system = ctx.system().map(|s| s.as_str());
+        let file = match FileMeta::from_dir_walker(entry, system, ctx.config(), &extractors).await {
+            Ok(file) => file,
+            Err(err) => {
+                let pass = report_io_error(ctx, &entry_path, &err, &send)?;
+                record(&mut summary, system.unwrap_or("unknown"), pass);
+                continue;
+            }
+        };
+
         let system = file.system().unwrap_or("unknown");
-        let pass = check(&ctx, &file, &send)?;
+        let pass = check(ctx, &file, &send)?;
+
+        if let Some(stem) = file.path().file_stem().and_then(|s| s.to_str()) {
+            let stems = stems.entry(system.to_owned()).or_default();
+            stems.insert(stem.to_owned());
+        }
+
+        record(&mut summary, system, pass);
+
+        let relative = ctx.relative_path(file.path()).unwrap_or_default();
+        let relative = relative.to_string_lossy().into_owned();
+
+        if lints_collection && file.system().is_some() && !file.metadata().is_dir() {
+            collections
+                .entry(system.to_owned())
+                .or_default()
+                .push(CollectionFile {
+                    path: relative.clone(),
+                    size: file.metadata().len(),
+                    archive_files: file.archive().map(|archive| {
+                        archive
+                            .file_names()
+                            .map(|name| name.to_string_lossy().into_owned())
+                            .collect()
+                    }),
+                });
+        }
 
         if pass {
-            summary.add_success(system);
-        } else {
-            summary.add_failure(system);
+            passed.insert(relative);
         }
     }
 
+    for (system, files) in &collections {
+        for path in check_collection(ctx, system, files, &send)? {
+            if passed.remove(&path) {
+                summary.fail_passed(system.as_str());
+            }
+        }
+    }
+
+    for (system, db) in ctx.databases().systems() {
+        let local = stems.remove(system).unwrap_or_default();
+        summary.set_collection(system, collection(db, &local));
+    }
+
     summary.mark_ended();
 
     send(Message::Finished(summary))
 }
+
+fn record(summary: &mut Summary, system: &str, pass: bool) {
+    if pass {
+        summary.add_success(system);
+    } else {
+        summary.add_failure(system);
+    }
+}
+
+/// Report a file which couldn't be read as a failure of the `io` lint, so that one bad file
+/// doesn't stop the rest of the collection from being linted
+fn report_io_error<F>(ctx: &LintContext, path: &Path, err: &io::Error, send: F) -> Result<bool>
+where
+    F: Fn(Message) -> Result<()>,
+{
+    let relative = ctx
+        .relative_path(path)
+        .and_then(|p| p.to_str().map(|s| s.to_owned()))
+        .context(InvalidPathErr { path })?;
+
+    let diagnostic = Diagnostic::new(path, err.to_string()).with_lint(IO_LINT);
+    let diagnostics = ctx.filter_baseline(&relative, vec![diagnostic]);
+    let pass = diagnostics.is_empty();
+
+    send(Message::Report(Report {
+        diagnostics,
+        path: relative,
+        system: ctx.system().cloned(),
+    }))?;
+
+    Ok(pass)
+}
+
+/// Count the games from a system's DATs which are in the collection, based on file names
+fn collection(db: &Database, local: &HashSet<String>) -> Collection {
+    let mut collection = Collection {
+        have: 0,
+        missing: 0,
+    };
+
+    for entry in db.files().filter(|entry| entry.is_collectable()) {
+        if local.contains(entry.game.name.as_str()) {
+            collection.have += 1;
+        } else {
+            collection.missing += 1;
+        }
+    }
+
+    collection
+}
@@ -0,0 +1,455 @@
+use super::walk::collection_entries;
+use crate::{
+    args::{Args, SyncNamesArgs},
+    checksum,
+    config::Config,
+    db::{read_dat, Dat, Database},
+    error::{IoErr, JournalErr, JournalExistsErr, Result},
+};
+use dat::Crc32;
+use serde::{Deserialize, Serialize};
+use snafu::prelude::*;
+use std::{
+    collections::{HashMap, HashSet},
+    fs::{self, File},
+    io::{self, BufReader},
+    path::{Path, PathBuf},
+};
+use zip::{ZipArchive, ZipWriter};
+
+/// A record of every rename performed, so that a sync can be reverted. Renaming a file inside of a
+/// zip and renaming the zip itself are recorded separately, so that either one can be undone even
+/// if the other failed.
+#[derive(Default, Deserialize, Serialize)]
+struct Journal {
+    renames: Vec<Rename>,
+}
+
+impl Journal {
+    fn save(&self, path: &Path) -> Result<()> {
+        let serialized = serde_json::to_string_pretty(self).context(JournalErr { path })?;
+        fs::write(path, serialized).context(IoErr { path })
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+struct Rename {
+    from: PathBuf,
+    to: PathBuf,
+    member: Option<MemberRename>,
+}
+
+/// A file inside of a zip archive which was renamed along with the archive itself
+#[derive(Clone, Deserialize, Serialize)]
+struct MemberRename {
+    from: String,
+    to: String,
+}
+
+/// Rename local files whose DAT entries were renamed between two versions of a DAT
+pub async fn sync_names(args: &Args, sync_args: &SyncNamesArgs) -> Result<()> {
+    let cwd = args.cwd();
+    if let Some(journal) = &sync_args.undo {
+        return undo(&cwd.join(journal), sync_args.dry_run);
+    }
+
+    // clap guarantees these are present when not undoing
+    let from = cwd.join(sync_args.from.as_deref().unwrap_or_default());
+    let to = cwd.join(sync_args.to.as_deref().unwrap_or_default());
+
+    let old = read_dat(&from).await?;
+    let new = read_dat(&to).await?;
+    let renames = dat::diff(&old, &new)
+        .renamed
+        .iter()
+        .map(|change| (change.old.name.clone(), change.new.name.clone()))
+        .collect::<HashMap<_, _>>();
+
+    let mut old_db = Database::default();
+    old_db.add(&from, Dat::new(old));
+
+    let config = Config::from_path(args.config_path()).await?;
+    let entries = collection_entries(args, &config, false).await?;
+
+    let mut planned = Vec::new();
+    let mut targets = HashSet::new();
+    for file in entries.iter().filter(|entry| entry.meta.is_file()) {
+        let rename = plan_rename(&file.path, &renames, &old_db, sync_args.by_hash)
+            .context(IoErr { path: &file.path })?;
+
+        if let Some(rename) = rename {
+            // Only the file inside of a zip is renamed when the zip already has its new name
+            if rename.to != rename.from && rename.to.exists() {
+                log::warn!("not renaming over existing file {}", rename.to.display());
+                continue;
+            }
+
+            if !targets.insert(rename.to.clone()) {
+                log::warn!(
+                    "not renaming {}, since another file is already being renamed to {}",
+                    rename.from.display(),
+                    rename.to.display()
+                );
+                continue;
+            }
+
+            planned.push(rename);
+        }
+    }
+
+    if sync_args.dry_run {
+        planned.iter().for_each(print_rename);
+        return Ok(());
+    }
+
+    let journal_path = cwd.join(&sync_args.journal);
+    if !planned.is_empty() && journal_path.exists() {
+        return JournalExistsErr { path: journal_path }.fail();
+    }
+
+    // The journal is saved after every rename, so that everything done so far can be undone
+    // even when a later rename fails
+    let mut journal = Journal::default();
+    for rename in &planned {
+        print_rename(rename);
+
+        let result = apply_recorded(rename, &mut journal);
+        journal.save(&journal_path)?;
+        result.context(IoErr { path: &rename.from })?;
+    }
+
+    if !journal.renames.is_empty() {
+        println!("Wrote undo journal to {}", journal_path.display());
+    }
+
+    Ok(())
+}
+
+/// Revert every rename in a journal, most recent first. The journal is removed once everything in
+/// it has been reverted.
+fn undo(journal_path: &Path, dry_run: bool) -> Result<()> {
+    let s = fs::read_to_string(journal_path).context(IoErr { path: journal_path })?;
+    let journal: Journal = serde_json::from_str(&s).context(JournalErr { path: journal_path })?;
+
+    for rename in journal.renames.iter().rev() {
+        let reverse = Rename {
+            from: rename.to.clone(),
+            to: rename.from.clone(),
+            member: rename.member.as_ref().map(|member| MemberRename {
+                from: member.to.clone(),
+                to: member.from.clone(),
+            }),
+        };
+
+        print_rename(&reverse);
+
+        if !dry_run {
+            apply(&reverse).context(IoErr {
+                path: &reverse.from,
+            })?;
+        }
+    }
+
+    if !dry_run {
+        fs::remove_file(journal_path).context(IoErr { path: journal_path })?;
+    }
+
+    Ok(())
+}
+
+/// Work out whether `path` refers to a renamed DAT entry, and if so, how it should be renamed.
+/// Files are matched by name, and optionally by CRC when their name is not recognized.
+fn plan_rename(
+    path: &Path,
+    renames: &HashMap<String, String>,
+    old_db: &Database,
+    by_hash: bool,
+) -> io::Result<Option<Rename>> {
+    let Some(stem) = path.file_stem().and_then(|s| s.to_str()) else {
+        return Ok(None);
+    };
+    let extension = path.extension().and_then(|s| s.to_str());
+    let is_zip = extension == Some("zip");
+
+    let members = if is_zip {
+        zip_members(path)?
+    } else {
+        Vec::new()
+    };
+
+    let mut old_name = renames.contains_key(stem).then(|| stem.to_owned());
+
+    if old_name.is_none() && by_hash {
+        old_name = if is_zip {
+            members
+                .iter()
+                .find_map(|(_, crc, size)| lookup_crc(old_db, *crc, *size, renames))
+        } else {
+            let size = fs::metadata(path)?.len();
+            lookup_crc(old_db, file_crc(path)?, size, renames)
+        };
+    }
+
+    let Some(old_name) = old_name else {
+        return Ok(None);
+    };
+
+    let new_name = &renames[&old_name];
+    let to = path.with_file_name(with_extension(new_name, extension));
+
+    let member = members
+        .iter()
+        .map(|(name, _, _)| Path::new(name))
+        .find(|member| {
+            let member_stem = member.file_stem().and_then(|s| s.to_str());
+            member_stem == Some(old_name.as_str()) || member_stem == Some(stem)
+        })
+        .map(|member| {
+            let extension = member.extension().and_then(|s| s.to_str());
+            let renamed = member.with_file_name(with_extension(new_name, extension));
+
+            MemberRename {
+                from: member.to_string_lossy().into_owned(),
+                to: renamed.to_string_lossy().into_owned(),
+            }
+        });
+
+    if to == path && member.is_none() {
+        return Ok(None);
+    }
+
+    Ok(Some(Rename {
+        from: path.to_path_buf(),
+        to,
+        member,
+    }))
+}
+
+/// DAT names often contain periods, so `Path::set_extension` can't be used to add an extension
+fn with_extension(name: &str, extension: Option<&str>) -> String {
+    match extension {
+        Some(extension) => format!("{name}.{extension}"),
+        None => name.to_owned(),
+    }
+}
+
+/// The first game with a ROM of the given CRC and size which was renamed
+fn lookup_crc(
+    db: &Database,
+    crc: u32,
+    size: u64,
+    renames: &HashMap<String, String>,
+) -> Option<String> {
+    db.by_crc(&Crc32::from(crc))
+        .into_iter()
+        .filter(|rom| rom.rom.size as u64 == size)
+        .map(|rom| &rom.entry.game.name)
+        .find(|name| renames.contains_key(*name))
+        .cloned()
+}
+
+fn apply(rename: &Rename) -> io::Result<()> {
+    if let Some(member) = &rename.member {
+        rename_zip_member(&rename.from, &member.from, &member.to)?;
+    }
+
+    if rename.from != rename.to {
+        fs::rename(&rename.from, &rename.to)?;
+    }
+
+    Ok(())
+}
+
+/// Apply a rename, recording each step in the journal once it has been done
+fn apply_recorded(rename: &Rename, journal: &mut Journal) -> io::Result<()> {
+    if let Some(member) = &rename.member {
+        rename_zip_member(&rename.from, &member.from, &member.to)?;
+        journal.renames.push(Rename {
+            from: rename.from.clone(),
+            to: rename.from.clone(),
+            member: Some(member.clone()),
+        });
+    }
+
+    if rename.from != rename.to {
+        fs::rename(&rename.from, &rename.to)?;
+        journal.renames.push(Rename {
+            from: rename.from.clone(),
+            to: rename.to.clone(),
+            member: None,
+        });
+    }
+
+    Ok(())
+}
+
+fn print_rename(rename: &Rename) {
+    println!("{} -> {}", rename.from.display(), rename.to.display());
+
+    if let Some(member) = &rename.member {
+        println!("   └─ {} -> {}", member.from, member.to);
+    }
+}
+
+/// The name, CRC and uncompressed size of each file in a zip archive
+fn zip_members(path: &Path) -> io::Result<Vec<(String, u32, u64)>> {
+    let mut zip = ZipArchive::new(BufReader::new(File::open(path)?))?;
+    let mut members = Vec::with_capacity(zip.len());
+
+    for i in 0..zip.len() {
+        let file = zip.by_index_raw(i)?;
+        members.push((file.name().to_owned(), file.crc32(), file.size()));
+    }
+
+    Ok(members)
+}
+
+/// Rewrite a zip archive with one of its files renamed. Data is copied without recompressing,
+/// into a temporary file which replaces the archive once it's complete.
+fn rename_zip_member(path: &Path, from: &str, to: &str) -> io::Result<()> {
+    let mut zip = ZipArchive::new(BufReader::new(File::open(path)?))?;
+    let mut tmp_name = path.as_os_str().to_owned();
+    tmp_name.push(".tmp");
+    let tmp_path = PathBuf::from(tmp_name);
+
+    let result = copy_renamed(&mut zip, &tmp_path, from, to);
+    if result.is_err() {
+        let _ = fs::remove_file(&tmp_path);
+    }
+    result?;
+
+    fs::rename(tmp_path, path)
+}
+
+fn copy_renamed<R: io::Read + io::Seek>(
+    zip: &mut ZipArchive<R>,
+    tmp_path: &Path,
+    from: &str,
+    to: &str,
+) -> io::Result<()> {
+    let mut writer = ZipWriter::new(File::create(tmp_path)?);
+
+    for i in 0..zip.len() {
+        let file = zip.by_index_raw(i)?;
+
+        if file.name() == from {
+            writer.raw_copy_file_rename(file, to)?;
+        } else {
+            writer.raw_copy_file(file)?;
+        }
+    }
+
+    writer.finish()?;
+    Ok(())
+}
+
+fn file_crc(path: &Path) -> io::Result<u32> {
+    checksum::crc32(BufReader::new(File::open(path)?))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::args::Command;
+    use clap::Parser;
+    use std::io::Write;
+    use zip::write::SimpleFileOptions;
+
+    const ROM: &[u8] = b"tetris";
+
+    fn write_dat(dir: &Path, file_name: &str, game: &str) {
+        let crc = crc32fast::hash(ROM);
+        let xml = format!(
+            r#"<datafile>
+<header><name>gb</name></header>
+<game name="{game}"><rom name="{game}.gb" size="{}" crc="{crc:08x}"/></game>
+</datafile>"#,
+            ROM.len()
+        );
+        fs::write(dir.join(file_name), xml).unwrap();
+    }
+
+    /// A collection whose DAT renamed "Old Name" to "New Name"
+    fn collection() -> tempfile::TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        let config = r#"
+            [global]
+            db_dir = "dats"
+
+            [system.gb]
+            archive_format = "zip"
+            raw_format = "gb"
+        "#;
+        fs::write(dir.path().join("romlint.toml"), config).unwrap();
+        fs::create_dir(dir.path().join("dats")).unwrap();
+        fs::create_dir(dir.path().join("gb")).unwrap();
+        write_dat(&dir.path().join("dats"), "old.dat", "Old Name");
+        write_dat(&dir.path().join("dats"), "new.dat", "New Name");
+
+        dir
+    }
+
+    async fn run(dir: &Path, args: &[&str]) {
+        let cwd = dir.to_str().unwrap();
+        let args = Args::parse_from(["romlint", "-c", cwd, "sync-names"].iter().chain(args));
+        let Command::SyncNames(sync_args) = &args.command else {
+            unreachable!();
+        };
+
+        sync_names(&args, sync_args).await.unwrap();
+    }
+
+    fn zip_names(path: &Path) -> Vec<String> {
+        zip_members(path)
+            .unwrap()
+            .into_iter()
+            .map(|(name, _, _)| name)
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn undoes_renames_relative_to_cwd() {
+        let dir = collection();
+        let gb = dir.path().join("gb");
+        fs::write(gb.join("Old Name.gb"), ROM).unwrap();
+
+        run(
+            dir.path(),
+            &["--from", "dats/old.dat", "--to", "dats/new.dat"],
+        )
+        .await;
+        assert!(gb.join("New Name.gb").is_file());
+        assert!(!gb.join("Old Name.gb").exists());
+        assert!(dir.path().join("romlint-sync-names.json").is_file());
+
+        run(dir.path(), &["--undo", "romlint-sync-names.json"]).await;
+        assert!(gb.join("Old Name.gb").is_file());
+        assert!(!gb.join("New Name.gb").exists());
+        assert!(!dir.path().join("romlint-sync-names.json").exists());
+    }
+
+    #[tokio::test]
+    async fn renames_members_of_already_renamed_zips() {
+        let dir = collection();
+        let zip_path = dir.path().join("gb").join("New Name.zip");
+        let mut zip = ZipWriter::new(File::create(&zip_path).unwrap());
+        zip.start_file("Old Name.gb", SimpleFileOptions::default())
+            .unwrap();
+        zip.write_all(ROM).unwrap();
+        zip.finish().unwrap();
+
+        let args = [
+            "--from",
+            "dats/old.dat",
+            "--to",
+            "dats/new.dat",
+            "--by-hash",
+        ];
+        run(dir.path(), &args).await;
+        assert_eq!(zip_names(&zip_path), ["New Name.gb"]);
+        assert!(!dir.path().join("gb").join("New Name.zip.tmp").exists());
+
+        run(dir.path(), &["--undo", "romlint-sync-names.json"]).await;
+        assert_eq!(zip_names(&zip_path), ["Old Name.gb"]);
+    }
+}
@@ -0,0 +1,106 @@
+use crate::{
+    args::Args,
+    config::Config,
+    error::{IoErr, Result},
+    filter::PathFilter,
+    ignore::{Ignores, IGNORE_FILE},
+};
+use dir_walker::{FileMeta, WalkOptions};
+use futures::StreamExt;
+use snafu::prelude::*;
+
+/// Every file and directory under the scan directory which is walked by `lint`, sorted by path.
+/// The config's include and exclude globs are applied, and unless `no_ignore` is set, so are
+/// ignore files. Entries which can't be read are logged and skipped, so that one bad file
+/// doesn't stop a whole command.
+pub async fn collection_entries(
+    args: &Args,
+    config: &Config,
+    no_ignore: bool,
+) -> Result<Vec<FileMeta>> {
+    let cwd = args.cwd();
+    let path = args.scan_dir();
+    let filter = PathFilter::new(&cwd, config)?;
+    let mut ignores = (!no_ignore).then(|| Ignores::new(&cwd));
+
+    let mut stream = Box::pin(
+        WalkOptions::new()
+            .sorted(true)
+            .filter(move |path, is_dir| filter.allows(path, is_dir))
+            .walk(&path)
+            .await
+            .context(IoErr { path: &path })?,
+    );
+
+    let mut entries = Vec::new();
+    while let Some(entry) = stream.next().await {
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(err) => {
+                log::warn!("skipping {err}");
+                continue;
+            }
+        };
+
+        let is_ignore_file = entry
+            .path
+            .file_name()
+            .is_some_and(|name| name == IGNORE_FILE);
+        let is_ignored = match ignores.as_mut() {
+            Some(ignores) => ignores.is_ignored(&entry.path)?,
+            None => false,
+        };
+
+        if !is_ignore_file && !is_ignored {
+            entries.push(entry);
+        }
+    }
+
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{cache::CACHE_DIR, db, ui::nop};
+    use clap::Parser;
+    use std::fs;
+
+    #[tokio::test]
+    async fn skips_the_database_cache() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        let config = r#"
+            [global]
+            db_dir = "dats"
+
+            [system.gb]
+            archive_format = "zip"
+            raw_format = "gb"
+        "#;
+        fs::write(root.join("romlint.toml"), config).unwrap();
+        fs::create_dir(root.join("dats")).unwrap();
+        fs::create_dir(root.join("gb")).unwrap();
+        fs::write(root.join("gb").join("Game.gb"), b"game").unwrap();
+        let dat = r#"<datafile>
+<header><name>gb</name></header>
+<game name="Game"><rom name="Game.gb" size="4" crc="00000000"/></game>
+</datafile>"#;
+        fs::write(root.join("dats").join("gb.dat"), dat).unwrap();
+
+        let args = Args::parse_from(["romlint", "-c", root.to_str().unwrap(), "dump"]);
+        let config = Config::from_path(args.config_path()).await.unwrap();
+        db::load_all(root.join("dats"), &config, &nop)
+            .await
+            .unwrap();
+        assert!(root.join(CACHE_DIR).is_dir());
+
+        let entries = collection_entries(&args, &config, false).await.unwrap();
+        assert!(entries
+            .iter()
+            .any(|entry| entry.path.ends_with("gb/Game.gb")));
+        assert!(entries
+            .iter()
+            .all(|entry| !entry.path.starts_with(root.join(CACHE_DIR))));
+    }
+}
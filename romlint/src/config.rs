@@ -30,15 +30,32 @@ impl Config {
 #[derive(Debug, Deserialize)]
 pub struct GlobalConfig {
     db_dir: String,
+    /// Globs, relative to the collection, of the only files which are linted. `*` doesn't match
+    /// across directories, so use `**` to match at any depth.
+    #[serde(default, deserialize_with = "string_or_vec")]
+    include: Vec<String>,
+    /// Globs, relative to the collection, of files and directories which aren't linted
+    #[serde(default, deserialize_with = "string_or_vec")]
+    exclude: Vec<String>,
 }
 
 #[derive(Debug, Deserialize)]
 pub struct SystemConfig {
     #[serde(deserialize_with = "string_or_vec")]
     archive_format: Vec<String>,
+    /// Paths or globs, relative to `db_dir`, of the DATs for this system. Each must match exactly
+    /// one file. When omitted, a DAT is matched by its header name or its file name.
+    #[serde(default, deserialize_with = "string_or_vec")]
+    dat: Vec<String>,
     obsolete_formats: Option<Vec<String>>,
     #[serde(deserialize_with = "string_or_vec")]
     raw_format: Vec<String>,
+    /// Like the global `include`, but relative to the system's directory
+    #[serde(default, deserialize_with = "string_or_vec")]
+    include: Vec<String>,
+    /// Like the global `exclude`, but relative to the system's directory
+    #[serde(default, deserialize_with = "string_or_vec")]
+    exclude: Vec<String>,
 }
 
 impl Config {
@@ -56,6 +73,30 @@ impl Config {
     pub fn db_dir(&self) -> &str {
         self.global.db_dir.as_str()
     }
+
+    pub fn systems(&self) -> impl Iterator<Item = &str> {
+        self.systems.keys().map(|s| s.as_str())
+    }
+
+    /// The global include and exclude globs
+    pub fn scan_patterns(&self) -> (&[String], &[String]) {
+        (&self.global.include, &self.global.exclude)
+    }
+
+    /// The include and exclude globs for a single system
+    pub fn system_scan_patterns(&self, system: &str) -> (&[String], &[String]) {
+        self.systems
+            .get(system)
+            .map(|sys| (sys.include.as_slice(), sys.exclude.as_slice()))
+            .unwrap_or_default()
+    }
+
+    pub fn dat_patterns(&self, system: &str) -> &[String] {
+        self.systems
+            .get(system)
+            .map(|sys| sys.dat.as_slice())
+            .unwrap_or_default()
+    }
 }
 
 pub struct ResolvedConfig<'a> {
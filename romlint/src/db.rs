@@ -1,17 +1,144 @@
-use crate::error::{DatabaseNameErr, DatabaseReadErr, IoErr, Result};
+use crate::cache::DatCache;
+use crate::config::Config;
+use crate::error::{
+    DatabaseMissingErr, DatabaseNameErr, DatabasePatternErr, DatabaseReadErr, DuplicateDatabaseErr,
+    IoErr, Result,
+};
 use crate::ui::Message;
 use crate::word_match::Tokens;
-use dat::{DataFile, Game};
+use dat::{Crc32, DataFile, ForceNoDump, Game, Header, Md5, Rom, Sha1, Sha256};
 use futures::future::try_join_all;
 use futures::TryFutureExt;
+use glob::glob;
+use serde::{Deserialize, Serialize};
 use snafu::prelude::*;
+use std::cmp::Reverse;
 use std::collections::HashMap;
-use std::path::Path;
-use tokio::fs::{read_dir, read_to_string};
+use std::fs::File;
+use std::hash::Hash;
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+use tokio::fs::read_dir;
+use tokio::task::spawn_blocking;
 
 #[derive(Default)]
 pub struct Databases(HashMap<String, Database>);
-pub struct Database(DataFile);
+
+/// All of the DATs for a single system. Lookups search each DAT in the order they were
+/// configured, so earlier DATs take precedence when an entry appears in several of them.
+#[derive(Default)]
+pub struct Database {
+    sources: Vec<Source>,
+}
+
+/// A parsed DAT and the file it was read from
+struct Source {
+    path: PathBuf,
+    dat: Dat,
+}
+
+/// A game from a `Database`, along with the DAT file that it came from
+#[derive(Clone, Copy)]
+pub struct Entry<'a> {
+    pub game: &'a Game,
+    pub header: &'a Header,
+    pub source: &'a Path,
+}
+
+/// A ROM from a `Database`, along with the game that it belongs to
+#[derive(Clone, Copy)]
+pub struct RomEntry<'a> {
+    pub entry: Entry<'a>,
+    pub rom: &'a Rom,
+}
+
+impl Source {
+    fn entry<'a>(&'a self, game: &'a Game) -> Entry<'a> {
+        Entry {
+            game,
+            header: self.dat.header(),
+            source: &self.path,
+        }
+    }
+}
+
+impl<'a> Entry<'a> {
+    pub fn source_name(&self) -> String {
+        self.source
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default()
+    }
+
+    /// Whether the game belongs in a complete collection. Games which were never dumped don't,
+    /// unless a ClrMamePro header requires them anyway.
+    pub fn is_collectable(&self) -> bool {
+        let required = self.header.clrmamepro.force_no_dump == Some(ForceNoDump::Required);
+        required || !self.game.is_nodump()
+    }
+}
+
+/// A single parsed DAT along with its lookup indexes. This is the unit stored in the cache.
+#[derive(Deserialize, Serialize)]
+pub struct Dat {
+    datafile: DataFile,
+    index: Index,
+}
+
+/// Lookup tables from a game's name to its position in the DAT, and from ROM hashes to the
+/// position of each game and ROM with that hash. Several games can share a ROM, so none of them
+/// are dropped.
+#[derive(Default, Deserialize, Serialize)]
+struct Index {
+    names: HashMap<String, usize>,
+    titles: HashMap<String, Vec<usize>>,
+    crcs: HashMap<Crc32, Vec<(usize, usize)>>,
+    md5s: HashMap<Md5, Vec<(usize, usize)>>,
+    sha1s: HashMap<Sha1, Vec<(usize, usize)>>,
+    sha256s: HashMap<Sha256, Vec<(usize, usize)>>,
+}
+
+impl Index {
+    fn new(games: &[Game]) -> Self {
+        let mut index = Self::default();
+
+        for (i, game) in games.iter().enumerate() {
+            index.names.entry(game.name.clone()).or_insert(i);
+            index
+                .titles
+                .entry(base_title(&game.name).to_owned())
+                .or_default()
+                .push(i);
+
+            for (j, rom) in game.roms.iter().enumerate() {
+                insert_hash(&mut index.crcs, rom.crc, (i, j));
+                insert_hash(&mut index.md5s, rom.md5, (i, j));
+                insert_hash(&mut index.sha1s, rom.sha1, (i, j));
+                insert_hash(&mut index.sha256s, rom.sha256, (i, j));
+            }
+        }
+
+        index
+    }
+}
+
+/// The title of a game without any of the parenthesized or bracketed tags that follow it. For
+/// example, both "Tetris (World) (Rev 1)" and "Tetris (Japan) [b]" have the base title "Tetris".
+pub fn base_title(name: &str) -> &str {
+    let end = name.find(['(', '[']).unwrap_or(name.len());
+    name[..end].trim_end()
+}
+
+/// DATs leave hashes out for ROMs which haven't been dumped, and those should never match
+fn insert_hash<H: Eq + Hash>(
+    index: &mut HashMap<H, Vec<(usize, usize)>>,
+    hash: Option<H>,
+    position: (usize, usize),
+) {
+    if let Some(hash) = hash {
+        index.entry(hash).or_default().push(position);
+    }
+}
 
 impl Databases {
     pub fn is_empty(&self) -> bool {
@@ -26,49 +153,158 @@ impl Databases {
         self.0.values()
     }
 
+    /// Each system along with its database, ordered by system name
+    pub fn systems(&self) -> Vec<(&str, &Database)> {
+        let mut systems = self
+            .0
+            .iter()
+            .map(|(system, db)| (system.as_str(), db))
+            .collect::<Vec<_>>();
+
+        systems.sort_unstable_by_key(|(system, _)| *system);
+        systems
+    }
+
     pub fn get(&self, db: &str) -> Option<&Database> {
         self.0.get(db)
     }
 }
 
-impl Database {
-    pub async fn from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
-        let s = read_to_string(path.as_ref()).await.context(IoErr {
-            path: path.as_ref(),
-        })?;
-        let datafile = DataFile::from_file(&s).context(DatabaseReadErr {
-            path: path.as_ref(),
-        })?;
+impl Dat {
+    pub fn new(datafile: DataFile) -> Self {
+        let index = Index::new(&datafile.games);
+        Self { datafile, index }
+    }
+
+    pub fn header(&self) -> &Header {
+        &self.datafile.header
+    }
+
+    fn by_name(&self, name: &str) -> Option<&Game> {
+        self.index.names.get(name).map(|&i| &self.datafile.games[i])
+    }
+
+    fn by_hash<'s, H: Eq + Hash>(
+        &'s self,
+        index: &'s HashMap<H, Vec<(usize, usize)>>,
+        hash: &H,
+    ) -> impl Iterator<Item = (&'s Game, &'s Rom)> {
+        index.get(hash).into_iter().flatten().map(|&(i, j)| {
+            let game = &self.datafile.games[i];
+            (game, &game.roms[j])
+        })
+    }
+
+    fn by_title(&self, title: &str) -> impl Iterator<Item = &Game> {
+        self.index
+            .titles
+            .get(title)
+            .into_iter()
+            .flatten()
+            .map(|&i| &self.datafile.games[i])
+    }
+}
 
-        Ok(Self(datafile))
+impl Database {
+    pub fn add<P: Into<PathBuf>>(&mut self, path: P, dat: Dat) {
+        let path = path.into();
+        self.sources.push(Source { path, dat });
     }
 
-    pub fn files(&self) -> impl Iterator<Item = &Game> {
-        self.0.games.iter()
+    pub fn files(&self) -> impl Iterator<Item = Entry<'_>> {
+        self.sources.iter().flat_map(|source| {
+            source
+                .dat
+                .datafile
+                .games
+                .iter()
+                .map(|game| source.entry(game))
+        })
     }
 
     pub fn contains(&self, file: &str) -> bool {
-        self.0.games.iter().any(|game| &game.name == file)
+        self.by_name(file).is_some()
+    }
+
+    pub fn by_name(&self, name: &str) -> Option<Entry<'_>> {
+        self.find(|dat| dat.by_name(name))
+    }
+
+    /// Every ROM with the given CRC, in the order of the DATs and the games inside of them
+    pub fn by_crc(&self, crc: &Crc32) -> Vec<RomEntry<'_>> {
+        self.find_roms(|dat| dat.by_hash(&dat.index.crcs, crc))
+    }
+
+    pub fn by_md5(&self, md5: &Md5) -> Vec<RomEntry<'_>> {
+        self.find_roms(|dat| dat.by_hash(&dat.index.md5s, md5))
+    }
+
+    pub fn by_sha1(&self, sha1: &Sha1) -> Vec<RomEntry<'_>> {
+        self.find_roms(|dat| dat.by_hash(&dat.index.sha1s, sha1))
+    }
+
+    pub fn by_sha256(&self, sha256: &Sha256) -> Vec<RomEntry<'_>> {
+        self.find_roms(|dat| dat.by_hash(&dat.index.sha256s, sha256))
+    }
+
+    fn find<'s, F>(&'s self, lookup: F) -> Option<Entry<'s>>
+    where
+        F: Fn(&'s Dat) -> Option<&'s Game>,
+    {
+        self.sources
+            .iter()
+            .find_map(|source| lookup(&source.dat).map(|game| source.entry(game)))
+    }
+
+    fn find_roms<'s, F, I>(&'s self, lookup: F) -> Vec<RomEntry<'s>>
+    where
+        F: Fn(&'s Dat) -> I,
+        I: Iterator<Item = (&'s Game, &'s Rom)>,
+    {
+        self.sources
+            .iter()
+            .flat_map(|source| {
+                lookup(&source.dat).map(|(game, rom)| RomEntry {
+                    entry: source.entry(game),
+                    rom,
+                })
+            })
+            .collect()
+    }
+
+    /// Other versions of a game, such as other regions or revisions. Games are considered
+    /// alternatives when they share a base title.
+    pub fn alternatives(&self, name: &str) -> Vec<Entry<'_>> {
+        let title = base_title(name);
+
+        self.sources
+            .iter()
+            .flat_map(|source| {
+                source
+                    .dat
+                    .by_title(title)
+                    .filter(|game| game.name != name)
+                    .map(|game| source.entry(game))
+            })
+            .collect()
     }
 
     pub fn similar_to<'s, 'a: 's>(&'s self, tokens: &'a Tokens<'a>) -> Vec<&'s str> {
         let mut similarities = self
-            .0
-            .games
-            .iter()
-            .filter_map(|game| {
-                let game_tokens = Tokens::from_str(game.name.as_str());
+            .files()
+            .filter_map(|entry| {
+                let game_tokens = Tokens::from_str(entry.game.name.as_str());
                 let same_words = tokens.words_in_common_with(&game_tokens);
 
                 if game_tokens.word_count() == 1 && same_words >= 1 || same_words >= 2 {
-                    Some((same_words, game))
+                    Some((same_words, entry.game))
                 } else {
                     None
                 }
             })
             .collect::<Vec<_>>();
 
-        similarities.sort_unstable_by(|a, b| b.0.cmp(&a.0));
+        similarities.sort_unstable_by_key(|(same_words, _)| Reverse(*same_words));
 
         similarities
             .iter()
@@ -78,72 +314,336 @@ impl Database {
     }
 }
 
-pub async fn load_all<P, F>(path: P, send: &F) -> Result<Databases>
+/// Parse a single DAT on a blocking thread, bypassing the cache
+pub async fn read_dat<P: Into<PathBuf>>(path: P) -> Result<DataFile> {
+    let path = path.into();
+
+    spawn_blocking(move || parse_dat(&path))
+        .await
+        .expect("reading a database panicked")
+}
+
+/// Stream a DAT from disk. DATs can be hundreds of megabytes, so the file is never read into
+/// memory all at once.
+pub fn parse_dat(path: &Path) -> Result<DataFile> {
+    let file = File::open(path).context(IoErr { path })?;
+
+    DataFile::from_reader(BufReader::new(file)).context(DatabaseReadErr { path })
+}
+
+/// A DAT on disk, along with the system it was explicitly mapped to in the config (if any)
+struct Candidate {
+    path: PathBuf,
+    system: Option<String>,
+}
+
+pub async fn load_all<P, F>(path: P, config: &Config, send: &F) -> Result<Databases>
 where
     F: Fn(Message) -> Result<()>,
     P: AsRef<Path>,
 {
     let path = path.as_ref();
-    let mut readdir = read_dir(path).await.context(IoErr { path })?;
-    let mut futures = Vec::new();
-    let mut i = 0;
-    let mut databases = Databases::default();
+    let (mut candidates, unclaimed) = find_sources(path, config).await?;
+    candidates.extend(
+        unclaimed
+            .into_iter()
+            .map(|path| Candidate { path, system: None }),
+    );
+
+    load_sources(path, config, candidates, None, send).await
+}
+
+pub async fn load_only<P, F>(
+    path: P,
+    config: &Config,
+    systems: &[&str],
+    send: &F,
+) -> Result<Databases>
+where
+    P: AsRef<Path>,
+    F: Fn(Message) -> Result<()>,
+{
+    let path = path.as_ref();
+    let (candidates, unclaimed) = find_sources(path, config).await?;
+    let mut candidates = candidates
+        .into_iter()
+        .filter(|candidate| {
+            let system = candidate.system.as_deref().unwrap_or_default();
+            systems.contains(&system)
+        })
+        .collect::<Vec<_>>();
 
-    while let Some(entry) = readdir.next_entry().await.context(IoErr { path })? {
-        let idx = i;
-        i += 1;
+    // Systems without an explicit mapping are usually found by file name. Only fall back to
+    // parsing every unclaimed DAT (to check its header) if that doesn't work.
+    let mut scan_headers = false;
+    for &system in systems
+        .iter()
+        .filter(|&&sys| config.dat_patterns(sys).is_empty())
+    {
+        let named = unclaimed
+            .iter()
+            .filter(|path| system_from_stem(path) == Some(system))
+            .map(|path| Candidate {
+                path: path.clone(),
+                system: None,
+            })
+            .collect::<Vec<_>>();
+
+        scan_headers |= named.is_empty();
+        candidates.extend(named);
+    }
+
+    if scan_headers {
+        candidates.retain(|candidate| candidate.system.is_some());
+        candidates.extend(
+            unclaimed
+                .into_iter()
+                .map(|path| Candidate { path, system: None }),
+        );
+    }
+
+    load_sources(path, config, candidates, Some(systems), send).await
+}
+
+/// Find all DATs in `db_dir`. The first list contains DATs which were mapped to a system in the
+/// config, and the second list contains all other DATs.
+async fn find_sources(db_dir: &Path, config: &Config) -> Result<(Vec<Candidate>, Vec<PathBuf>)> {
+    let mut claimed = Vec::new();
+
+    for system in config.systems() {
+        for pattern in config.dat_patterns(system) {
+            let full_pattern = db_dir.join(pattern);
+            let full_pattern = full_pattern.to_string_lossy();
+            let mut paths = glob(&full_pattern)
+                .context(DatabasePatternErr { pattern })?
+                .filter_map(|path| path.ok())
+                .filter(|path| path.is_file());
+
+            let path = paths
+                .next()
+                .context(DatabaseMissingErr { system, pattern })?;
+            if let Some(other) = paths.next() {
+                return DuplicateDatabaseErr {
+                    system,
+                    first: path,
+                    second: other,
+                }
+                .fail();
+            }
+
+            let system = Some(system.to_owned());
+            claimed.push(Candidate { path, system });
+        }
+    }
+
+    let mut unclaimed = Vec::new();
+    let mut readdir = read_dir(db_dir).await.context(IoErr { path: db_dir })?;
+
+    while let Some(entry) = readdir.next_entry().await.context(IoErr { path: db_dir })? {
         let path = entry.path();
-        let system = path
-            .as_path()
+        let is_file = entry
+            .file_type()
+            .await
+            .context(IoErr { path: &path })?
+            .is_file();
+        let is_claimed = claimed
+            .iter()
+            .any(|candidate| normalize(&candidate.path) == normalize(&path));
+
+        if is_file && !is_claimed {
+            unclaimed.push(path);
+        }
+    }
+
+    Ok((claimed, unclaimed))
+}
+
+/// Load and merge the candidate DATs. When `systems` is given, DATs which turn out to belong to
+/// other systems are dropped, and can't conflict with each other.
+async fn load_sources<F>(
+    db_dir: &Path,
+    config: &Config,
+    candidates: Vec<Candidate>,
+    systems: Option<&[&str]>,
+    send: &F,
+) -> Result<Databases>
+where
+    F: Fn(Message) -> Result<()>,
+{
+    let cache = DatCache::for_db_dir(db_dir);
+    let mut futures = Vec::with_capacity(candidates.len());
+
+    for (i, candidate) in candidates.into_iter().enumerate() {
+        let label = candidate
+            .path
             .file_stem()
-            .and_then(|f| f.to_str())
+            .and_then(|s| s.to_str())
             .map(|s| s.to_owned())
             .context(DatabaseNameErr {
-                path: path.as_path(),
+                path: candidate.path.as_path(),
             })?;
 
-        send(Message::StartProgress(idx, system.clone()))?;
+        send(Message::StartProgress(i, label))?;
 
-        let future = Database::from_file(path)
-            .map_ok(|db| (system, db))
+        let future = cache
+            .load(candidate.path.clone())
+            .map_ok(|dat| (candidate, dat))
             .inspect_ok(move |_| {
-                send(Message::EndProgress(idx)).unwrap();
+                send(Message::EndProgress(i)).unwrap();
             });
         futures.push(future);
     }
 
-    let loaded_dbs = try_join_all(futures).await?.into_iter().collect::<Vec<_>>();
-    for (sys, db) in loaded_dbs {
-        databases.add(sys, db);
+    // DATs mapped to the same system in the config are merged. A DAT that was matched
+    // automatically must be the only DAT for its system.
+    let mut merged = HashMap::<String, Database>::new();
+    let mut claimed_by = HashMap::<String, (PathBuf, bool)>::new();
+
+    for (candidate, dat) in try_join_all(futures).await? {
+        let is_mapped = candidate.system.is_some();
+        let system = match candidate.system {
+            Some(system) => system,
+            None => match_system(config, &dat, &candidate.path),
+        };
+
+        if systems.is_some_and(|systems| !systems.contains(&system.as_str())) {
+            continue;
+        }
+
+        if let Some((first, first_mapped)) = claimed_by.get(&system) {
+            if !(is_mapped && *first_mapped) {
+                return DuplicateDatabaseErr {
+                    system,
+                    first: first.clone(),
+                    second: candidate.path,
+                }
+                .fail();
+            }
+        } else {
+            claimed_by.insert(system.clone(), (candidate.path.clone(), is_mapped));
+        }
+
+        merged.entry(system).or_default().add(candidate.path, dat);
+    }
+
+    let mut databases = Databases::default();
+    for (system, db) in merged {
+        databases.add(system, db);
     }
 
     Ok(databases)
 }
 
-pub async fn load_only<P, F>(path: P, systems: &[&str], send: &F) -> Result<Databases>
-where
-    P: AsRef<Path>,
-    F: Fn(Message) -> Result<()>,
-{
-    let mut databases = Databases::default();
-    let path = path.as_ref();
-    let futures = systems.iter().enumerate().map(|(i, sys)| {
-        let mut path = path.join(sys);
-        path.set_extension("dat");
-        send(Message::StartProgress(i, sys.to_string())).unwrap();
-
-        Database::from_file(path)
-            .map_ok(move |db| (i, sys.to_string(), db))
-            .inspect_ok(|(i, _, _)| {
-                send(Message::EndProgress(*i)).unwrap();
-            })
-            .map_ok(|(_, sys, db)| (sys, db))
-    });
+/// Work out which system an unmapped DAT belongs to. A configured system matches if its name is
+/// the same as the name in the DAT's header. Otherwise the system is named after the DAT's file.
+fn match_system(config: &Config, dat: &Dat, path: &Path) -> String {
+    let header_name = dat.header().name.as_str();
+    let by_header = config
+        .systems()
+        .filter(|sys| config.dat_patterns(sys).is_empty())
+        .find(|sys| sys.eq_ignore_ascii_case(header_name));
+
+    by_header
+        .or_else(|| system_from_stem(path))
+        .unwrap_or(header_name)
+        .to_owned()
+}
 
-    let loaded_dbs = try_join_all(futures).await?.into_iter().collect::<Vec<_>>();
-    for (sys, db) in loaded_dbs {
-        databases.add(sys, db);
+/// The system name implied by a DAT's file name. Release timestamps, as found in No-Intro
+/// downloads, are dropped so that `Nintendo - Game Boy (20240830-122750).dat` names
+/// `Nintendo - Game Boy`.
+fn system_from_stem(path: &Path) -> Option<&str> {
+    let stem = path.file_stem()?.to_str()?;
+    let trimmed = stem
+        .strip_suffix(')')
+        .and_then(|s| s.rsplit_once(" ("))
+        .filter(|(_, version)| version.chars().all(|c| c.is_ascii_digit() || c == '-'))
+        .map(|(name, _)| name);
+
+    Some(trimmed.unwrap_or(stem))
+}
+
+fn normalize(path: &Path) -> PathBuf {
+    path.components().collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::error::Error;
+    use crate::ui::nop;
+    use std::fs;
+
+    fn write_dat(dir: &Path, file_name: &str, header_name: &str) {
+        let xml = format!(
+            r#"<datafile>
+<header><name>{header_name}</name></header>
+<game name="Game"><rom name="Game.bin" size="1" crc="00000000"/></game>
+</datafile>"#
+        );
+        fs::write(dir.join(file_name), xml).unwrap();
     }
 
-    Ok(databases)
+    fn config(src: &str) -> Config {
+        toml::from_str(src).unwrap()
+    }
+
+    #[test]
+    fn finds_every_game_sharing_a_rom() {
+        let xml = r#"<datafile>
+<header><name>gb</name></header>
+<game name="Game (USA)"><rom name="Game (USA).gb" size="4" crc="12345678"/></game>
+<game name="Game (Europe)">
+<rom name="Manual.txt" size="1" crc="00000000"/>
+<rom name="Game (Europe).gb" size="4" crc="12345678"/>
+</game>
+</datafile>"#;
+        let mut db = Database::default();
+        db.add(
+            "gb.dat",
+            Dat::new(DataFile::from_reader(xml.as_bytes()).unwrap()),
+        );
+
+        let roms = db.by_crc(&"12345678".parse().unwrap());
+        let found = roms
+            .iter()
+            .map(|rom| (rom.entry.game.name.as_str(), rom.rom.name.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            found,
+            [
+                ("Game (USA)", "Game (USA).gb"),
+                ("Game (Europe)", "Game (Europe).gb")
+            ]
+        );
+        assert!(db.by_crc(&"87654321".parse().unwrap()).is_empty());
+    }
+
+    #[tokio::test]
+    async fn ignores_conflicts_of_other_systems_when_scanning_headers() {
+        let dir = tempfile::tempdir().unwrap();
+        let db_dir = dir.path().join("dats");
+        fs::create_dir(&db_dir).unwrap();
+        write_dat(&db_dir, "handheld.dat", "gb");
+        write_dat(&db_dir, "Other (20240101).dat", "Other");
+        write_dat(&db_dir, "Other (20240202).dat", "Other");
+
+        let config = config(
+            r#"
+            [global]
+            db_dir = "dats"
+
+            [system.gb]
+            archive_format = "zip"
+            raw_format = "gb"
+            "#,
+        );
+
+        let databases = load_only(&db_dir, &config, &["gb"], &nop).await.unwrap();
+        assert!(databases.0.contains_key("gb"));
+        assert_eq!(databases.0.len(), 1);
+
+        let err = load_all(&db_dir, &config, &nop).await.err().unwrap();
+        assert!(matches!(err, Error::DuplicateDatabase { system, .. } if system == "Other"));
+    }
 }
@@ -0,0 +1,169 @@
+use crate::checksum;
+use crate::db::Databases;
+use dat::Crc32;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{self, BufReader};
+use std::path::Path;
+use zip::ZipArchive;
+
+/// Some content of a file: either the whole file, or a single member when the file is an archive
+/// which can be read
+pub struct Content {
+    pub member: Option<String>,
+    pub size: u64,
+    pub crc: Crc32,
+}
+
+/// A file of the collection, along with everything it contains
+pub struct ScannedFile {
+    /// Relative to the collection
+    pub path: String,
+    pub system: Option<String>,
+    pub contents: Vec<Content>,
+}
+
+impl ScannedFile {
+    pub fn read(path: &Path, relative: String, system: Option<String>) -> io::Result<Self> {
+        Ok(Self {
+            path: relative,
+            system,
+            contents: read_contents(path)?,
+        })
+    }
+
+    fn stem(&self) -> Option<&str> {
+        Path::new(&self.path).file_stem().and_then(|s| s.to_str())
+    }
+}
+
+/// Everything a file contains: each member of a zip, or otherwise the whole file
+pub fn read_contents(path: &Path) -> io::Result<Vec<Content>> {
+    let is_zip = path.extension().and_then(|ext| ext.to_str()) == Some("zip");
+    if is_zip {
+        return zip_contents(path);
+    }
+
+    let file = File::open(path)?;
+    let size = file.metadata()?.len();
+    let crc = checksum::crc32(BufReader::new(file))?;
+
+    Ok(vec![Content {
+        member: None,
+        size,
+        crc: crc.into(),
+    }])
+}
+
+/// Zips record the size and CRC of each member, so nothing needs to be decompressed
+fn zip_contents(path: &Path) -> io::Result<Vec<Content>> {
+    let mut zip = ZipArchive::new(BufReader::new(File::open(path)?))?;
+    let mut contents = Vec::with_capacity(zip.len());
+
+    for i in 0..zip.len() {
+        let file = zip.by_index_raw(i)?;
+        if file.is_dir() {
+            continue;
+        }
+
+        contents.push(Content {
+            member: Some(file.name().to_owned()),
+            size: file.size(),
+            crc: file.crc32().into(),
+        });
+    }
+
+    Ok(contents)
+}
+
+/// Where a copy of some content was found
+#[derive(Serialize)]
+pub struct Location<'a> {
+    pub path: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub member: Option<&'a str>,
+}
+
+/// Identical content which was found in more than one place
+#[derive(Serialize)]
+pub struct ContentGroup<'a> {
+    pub size: u64,
+    pub crc: String,
+    pub locations: Vec<Location<'a>>,
+}
+
+/// Files which hold the same game from a DAT, whatever their container
+#[derive(Serialize)]
+pub struct GameGroup<'a> {
+    pub system: &'a str,
+    pub game: &'a str,
+    pub paths: Vec<&'a str>,
+}
+
+/// Group content which appears more than once by its size and CRC, including content inside
+/// archives. Empty files are left out, since they're always identical.
+pub fn by_content(files: &[ScannedFile]) -> Vec<ContentGroup<'_>> {
+    let mut groups = BTreeMap::<(u64, Crc32), Vec<Location>>::new();
+
+    for file in files {
+        for content in file.contents.iter().filter(|content| content.size > 0) {
+            groups
+                .entry((content.size, content.crc))
+                .or_default()
+                .push(Location {
+                    path: &file.path,
+                    member: content.member.as_deref(),
+                });
+        }
+    }
+
+    groups
+        .into_iter()
+        .filter(|(_, locations)| locations.len() > 1)
+        .map(|((size, crc), locations)| ContentGroup {
+            size,
+            crc: crc.to_string(),
+            locations,
+        })
+        .collect()
+}
+
+/// Group files which resolve to the same DAT entry, to find the same game stored in different
+/// containers. Files are matched by the CRC of their contents, or by name when that fails, as
+/// with archives which can't be read.
+pub fn by_game<'a>(files: &'a [ScannedFile], databases: &'a Databases) -> Vec<GameGroup<'a>> {
+    let mut groups = BTreeMap::<(&str, &str), Vec<&str>>::new();
+
+    for file in files {
+        let Some(system) = file.system.as_deref() else {
+            continue;
+        };
+        let Some(db) = databases.get(system) else {
+            continue;
+        };
+
+        let entry = file
+            .contents
+            .iter()
+            .find_map(|content| db.by_crc(&content.crc).first().map(|rom| rom.entry))
+            .or_else(|| file.stem().and_then(|stem| db.by_name(stem)));
+
+        if let Some(entry) = entry {
+            groups
+                .entry((system, entry.game.name.as_str()))
+                .or_default()
+                .push(&file.path);
+        }
+    }
+
+    groups
+        .into_iter()
+        .filter(|(_, paths)| paths.len() > 1)
+        .map(|((system, game), paths)| GameGroup {
+            system,
+            game,
+            paths,
+        })
+        .collect()
+}
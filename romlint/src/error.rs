@@ -8,6 +8,41 @@ pub enum Error {
     #[snafu(display("error reading {} database: {source}", path.display()))]
     DatabaseRead { path: PathBuf, source: dat::Error },
 
+    #[snafu(display("found {count} problem(s) in {}", path.display()))]
+    InvalidDatabase { path: PathBuf, count: usize },
+
+    #[snafu(display("error writing database cache {}: {source}", path.display()))]
+    CacheWrite {
+        path: PathBuf,
+        source: bincode::Error,
+    },
+
+    #[snafu(display("invalid DAT pattern '{pattern}': {source}"))]
+    DatabasePattern {
+        pattern: String,
+        source: glob::PatternError,
+    },
+
+    #[snafu(display("invalid scan pattern '{pattern}': {source}"))]
+    ScanPattern {
+        pattern: String,
+        source: glob::PatternError,
+    },
+
+    #[snafu(display("no DAT matching '{pattern}' was found for the system '{system}'"))]
+    DatabaseMissing { system: String, pattern: String },
+
+    #[snafu(display(
+        "both {} and {} are DATs for the system '{system}'",
+        first.display(),
+        second.display()
+    ))]
+    DuplicateDatabase {
+        system: String,
+        first: PathBuf,
+        second: PathBuf,
+    },
+
     #[snafu(display("unable to determine the system name of {}", path.display()))]
     DatabaseName { path: PathBuf },
 
@@ -17,9 +52,40 @@ pub enum Error {
     #[snafu(display("error accessing {}", path.display()))]
     Io { path: PathBuf, source: io::Error },
 
+    #[snafu(display("error reading {source}"))]
+    Walk { source: dir_walker::WalkError },
+
     #[snafu(display("unable to process path {}", path.display()))]
     InvalidPath { path: PathBuf },
 
+    #[snafu(display("error reading journal {}: {source}", path.display()))]
+    Journal {
+        path: PathBuf,
+        source: serde_json::Error,
+    },
+
+    #[snafu(display(
+        "journal {} already exists; undo or delete it, or choose another with --journal",
+        path.display()
+    ))]
+    JournalExists { path: PathBuf },
+
+    #[snafu(display("error reading baseline {}: {source}", path.display()))]
+    Baseline {
+        path: PathBuf,
+        source: serde_json::Error,
+    },
+
+    #[snafu(display("{count} file(s) have failures which aren't in the baseline"))]
+    NewFailures { count: usize },
+
+    #[snafu(display("invalid pattern in {} on line {line}: {source}", path.display()))]
+    IgnorePattern {
+        path: PathBuf,
+        line: usize,
+        source: glob::PatternError,
+    },
+
     #[snafu(display("attempted to send over a broken pipe"))]
     BrokenPipe { source: SendError<Message> },
 
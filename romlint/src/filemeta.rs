@@ -3,7 +3,7 @@ use dir_walker::FileMeta as DirMeta;
 use std::collections::HashMap;
 use std::fs::{File, Metadata};
 use std::{
-    io::{BufReader, Result},
+    io::{BufReader, Error, ErrorKind, Result},
     path::{Path, PathBuf},
 };
 use tokio::fs::metadata;
@@ -11,8 +11,6 @@ use zip::ZipArchive;
 
 pub struct ArchiveInfo {
     file_names: Vec<String>,
-    pub compressed_size: u64,
-    pub uncompressed_size: u64,
 }
 
 impl ArchiveInfo {
@@ -21,6 +19,254 @@ impl ArchiveInfo {
     }
 }
 
+/// The kind of sheet describing the tracks of a disc image
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SheetFormat {
+    Cue,
+    Gdi,
+}
+
+impl SheetFormat {
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let ext = path.extension()?.to_str()?;
+
+        if ext.eq_ignore_ascii_case("cue") {
+            Some(Self::Cue)
+        } else if ext.eq_ignore_ascii_case("gdi") {
+            Some(Self::Gdi)
+        } else {
+            None
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Cue => "cue",
+            Self::Gdi => "gdi",
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Track {
+    pub number: u32,
+    /// The track's type, like `MODE1/2352` or `AUDIO` in cue sheets, or `0` (audio) and `4`
+    /// (data) in GDI sheets
+    pub mode: String,
+    pub file: String,
+}
+
+/// A file referenced by a sheet or playlist, relative to the directory it's in
+pub struct LinkedFile {
+    pub name: String,
+    pub exists: bool,
+}
+
+/// A parsed cue or GDI sheet
+pub struct Sheet {
+    pub format: SheetFormat,
+    pub files: Vec<LinkedFile>,
+    pub tracks: Vec<Track>,
+}
+
+impl Sheet {
+    /// Read the sheet at `path`, checking whether each file it references exists next to it
+    pub fn read(path: &Path, format: SheetFormat) -> Result<Self> {
+        let src = std::fs::read_to_string(path)?;
+        let tracks = match format {
+            SheetFormat::Cue => parse_cue(&src),
+            SheetFormat::Gdi => parse_gdi(&src)?,
+        };
+
+        let dir = path.parent().unwrap_or(Path::new(""));
+        let mut files: Vec<LinkedFile> = Vec::new();
+        for track in &tracks {
+            if files.iter().all(|file| file.name != track.file) {
+                files.push(LinkedFile {
+                    name: track.file.clone(),
+                    exists: dir.join(&track.file).is_file(),
+                });
+            }
+        }
+
+        Ok(Self {
+            format,
+            files,
+            tracks,
+        })
+    }
+}
+
+/// An `.m3u` playlist of the discs of a multi-disc game
+pub struct Playlist {
+    pub entries: Vec<LinkedFile>,
+}
+
+impl Playlist {
+    /// Read the playlist at `path`, skipping blank lines and `#` comments
+    pub fn read(path: &Path) -> Result<Self> {
+        let src = std::fs::read_to_string(path)?;
+        let dir = path.parent().unwrap_or(Path::new(""));
+        let entries = src
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(|line| LinkedFile {
+                name: line.to_owned(),
+                exists: dir.join(line).is_file(),
+            })
+            .collect();
+
+        Ok(Self { entries })
+    }
+}
+
+/// Cue sheets list a `FILE` followed by the `TRACK`s inside of it. Commands which don't say
+/// anything about tracks, like `INDEX` or `REM`, are skipped.
+fn parse_cue(src: &str) -> Vec<Track> {
+    let mut tracks = Vec::new();
+    let mut file = None;
+
+    for line in src.lines() {
+        let line = line.trim();
+        let (command, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        let rest = rest.trim();
+
+        if command.eq_ignore_ascii_case("FILE") {
+            // The file type comes last, and names with spaces are quoted
+            file = match rest.strip_prefix('"') {
+                Some(quoted) => quoted.split_once('"').map(|(name, _)| name.to_owned()),
+                None => rest
+                    .rsplit_once(char::is_whitespace)
+                    .map(|(name, _)| name.trim().to_owned()),
+            };
+        } else if command.eq_ignore_ascii_case("TRACK") {
+            let mut words = rest.split_whitespace();
+            let number = words.next().and_then(|n| n.parse().ok());
+
+            if let (Some(number), Some(file)) = (number, &file) {
+                tracks.push(Track {
+                    number,
+                    mode: words.next().unwrap_or_default().to_owned(),
+                    file: file.clone(),
+                });
+            }
+        }
+    }
+
+    tracks
+}
+
+/// GDI sheets start with the number of tracks, followed by a line per track of its number, start
+/// sector, type, sector size, file name and offset. Names with spaces are quoted.
+fn parse_gdi(src: &str) -> Result<Vec<Track>> {
+    let mut lines = src.lines().map(str::trim).filter(|line| !line.is_empty());
+    let count = lines
+        .next()
+        .and_then(|line| line.parse::<usize>().ok())
+        .ok_or_else(|| invalid_gdi("missing track count"))?;
+
+    let mut tracks = Vec::with_capacity(count);
+    for line in lines {
+        let words = split_quoted(line);
+        let number = words.first().and_then(|n| n.parse().ok());
+
+        let (Some(number), Some(mode), Some(file)) = (number, words.get(2), words.get(4)) else {
+            return Err(invalid_gdi(&format!("malformed track '{line}'")));
+        };
+
+        tracks.push(Track {
+            number,
+            mode: mode.to_string(),
+            file: file.to_string(),
+        });
+    }
+
+    if tracks.len() != count {
+        let msg = format!("expected {count} tracks, found {}", tracks.len());
+        return Err(invalid_gdi(&msg));
+    }
+
+    Ok(tracks)
+}
+
+/// Split a line on whitespace, keeping quoted words together
+fn split_quoted(line: &str) -> Vec<&str> {
+    let mut words = Vec::new();
+    let mut rest = line.trim_start();
+
+    while !rest.is_empty() {
+        let (word, remainder) = match rest.strip_prefix('"') {
+            Some(quoted) => quoted.split_once('"').unwrap_or((quoted, "")),
+            None => rest.split_once(char::is_whitespace).unwrap_or((rest, "")),
+        };
+
+        words.push(word);
+        rest = remainder.trim_start();
+    }
+
+    words
+}
+
+fn invalid_gdi(msg: &str) -> Error {
+    Error::new(ErrorKind::InvalidData, format!("invalid GDI sheet: {msg}"))
+}
+
+/// A single entry of a directory, as given to directory lints
+pub struct ListingEntry {
+    pub name: String,
+    pub is_dir: bool,
+    pub is_file: bool,
+    pub size: u64,
+    /// Set for cue and GDI sheets which could be read
+    pub sheet: Option<Sheet>,
+}
+
+/// List the immediate contents of a directory, sorted by name. Symlinks are followed, and entries
+/// which can't be read, like broken symlinks, are listed as neither a file nor a directory. Those
+/// are reported when the entry itself is linted.
+pub fn read_listing(path: &Path) -> Result<Vec<ListingEntry>> {
+    let mut listing = Vec::new();
+
+    for entry in std::fs::read_dir(path)? {
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(err) => {
+                log::warn!("skipping an entry of {}: {err}", path.display());
+                continue;
+            }
+        };
+
+        let path = entry.path();
+        let meta = match std::fs::metadata(&path) {
+            Ok(meta) => meta,
+            Err(err) => {
+                log::debug!(
+                    "leaving {} out of its directory's listing: {err}",
+                    path.display()
+                );
+                continue;
+            }
+        };
+
+        // Sheets which can't be read are reported when the sheet itself is linted
+        let sheet = SheetFormat::from_path(&path)
+            .filter(|_| meta.is_file())
+            .and_then(|format| Sheet::read(&path, format).ok());
+
+        listing.push(ListingEntry {
+            name: entry.file_name().to_string_lossy().into_owned(),
+            is_dir: meta.is_dir(),
+            is_file: meta.is_file(),
+            size: meta.len(),
+            sheet,
+        });
+    }
+
+    listing.sort_unstable_by(|a, b| a.name.cmp(&b.name));
+    Ok(listing)
+}
+
 pub struct FileMeta<'a> {
     archive: Option<ArchiveInfo>,
     config: Option<ResolvedConfig<'a>>,
@@ -46,23 +292,15 @@ impl Extractor for ZipExtractor {
     fn extract(&self, path: &Path) -> Result<ArchiveInfo> {
         let file = File::open(path)?;
         let reader = BufReader::new(file);
-        let mut zip = ZipArchive::new(reader).unwrap();
-        let mut uncompressed_size = 0;
-        let mut compressed_size = 0;
+        let mut zip = ZipArchive::new(reader)?;
         let mut file_names = Vec::with_capacity(zip.len());
 
         for i in 0..zip.len() {
-            let file = zip.by_index_raw(i).unwrap();
-            uncompressed_size += file.size();
-            compressed_size += file.compressed_size();
+            let file = zip.by_index_raw(i)?;
             file_names.push(file.name().to_string());
         }
 
-        Ok(ArchiveInfo {
-            file_names,
-            uncompressed_size,
-            compressed_size,
-        })
+        Ok(ArchiveInfo { file_names })
     }
 }
 
@@ -132,7 +370,7 @@ impl<'a> FileMeta<'a> {
         Self::from_raw_parts(system, config, path, meta, depth, extractors).await
     }
 
-    pub fn config(&self) -> Option<&ResolvedConfig> {
+    pub fn config(&self) -> Option<&ResolvedConfig<'_>> {
         self.config.as_ref()
     }
 
@@ -148,6 +386,30 @@ impl<'a> FileMeta<'a> {
         self.archive.as_ref()
     }
 
+    /// Read and parse the file when it's a cue or GDI sheet. Sheets are only read by lints which
+    /// need them, so that one which can't be parsed only fails those lints.
+    pub fn sheet(&self) -> Result<Option<Sheet>> {
+        match SheetFormat::from_path(&self.path) {
+            Some(format) if self.meta.is_file() => Sheet::read(&self.path, format).map(Some),
+            _ => Ok(None),
+        }
+    }
+
+    /// Read the file when it's an `.m3u` playlist
+    pub fn playlist(&self) -> Result<Option<Playlist>> {
+        let is_m3u = self
+            .path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("m3u"));
+
+        if is_m3u && self.meta.is_file() {
+            Playlist::read(&self.path).map(Some)
+        } else {
+            Ok(None)
+        }
+    }
+
     pub fn system(&self) -> Option<&str> {
         if self.forced_system.is_some() {
             self.forced_system
@@ -158,3 +420,118 @@ impl<'a> FileMeta<'a> {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn files(tracks: &[Track]) -> Vec<&str> {
+        tracks.iter().map(|track| track.file.as_str()).collect()
+    }
+
+    #[test]
+    fn reads_quoted_cue_file_names() {
+        let tracks = parse_cue(
+            r#"FILE "Game (USA) (Track 1).bin" BINARY
+  TRACK 01 MODE1/2352
+    INDEX 01 00:00:00"#,
+        );
+
+        assert_eq!(files(&tracks), ["Game (USA) (Track 1).bin"]);
+        assert_eq!(tracks[0].number, 1);
+        assert_eq!(tracks[0].mode, "MODE1/2352");
+    }
+
+    #[test]
+    fn reads_unquoted_cue_file_names() {
+        let tracks = parse_cue("FILE game.bin BINARY\n  TRACK 01 MODE2/2352\n");
+        assert_eq!(files(&tracks), ["game.bin"]);
+    }
+
+    #[test]
+    fn reads_multiple_tracks_per_cue_file() {
+        let tracks = parse_cue(
+            r#"FILE "Game.bin" BINARY
+  TRACK 01 MODE1/2352
+    INDEX 01 00:00:00
+  TRACK 02 AUDIO
+    INDEX 00 10:00:00
+    INDEX 01 10:02:00
+FILE "Game (Track 3).bin" BINARY
+  TRACK 03 AUDIO
+    INDEX 01 00:00:00"#,
+        );
+
+        let numbers = tracks.iter().map(|track| track.number).collect::<Vec<_>>();
+        assert_eq!(numbers, [1, 2, 3]);
+        assert_eq!(
+            files(&tracks),
+            ["Game.bin", "Game.bin", "Game (Track 3).bin"]
+        );
+        assert_eq!(tracks[1].mode, "AUDIO");
+    }
+
+    #[test]
+    fn skips_cue_tracks_without_a_file() {
+        assert!(parse_cue("TRACK 01 MODE1/2352\n").is_empty());
+    }
+
+    #[test]
+    fn reads_cue_sheets_with_crlf_line_endings() {
+        let tracks = parse_cue("FILE \"Game.bin\" BINARY\r\n  TRACK 01 MODE1/2352\r\n");
+        assert_eq!(files(&tracks), ["Game.bin"]);
+        assert_eq!(tracks[0].mode, "MODE1/2352");
+    }
+
+    #[test]
+    fn reads_gdi_sheets() {
+        let tracks = parse_gdi(
+            "3
+1 0 4 2352 track01.bin 0
+2 756 0 2352 track02.raw 0
+3 45000 4 2352 track03.bin 0",
+        )
+        .unwrap();
+
+        assert_eq!(
+            files(&tracks),
+            ["track01.bin", "track02.raw", "track03.bin"]
+        );
+        assert_eq!(tracks[1].number, 2);
+        assert_eq!(tracks[1].mode, "0");
+    }
+
+    #[test]
+    fn reads_quoted_gdi_file_names() {
+        let tracks = parse_gdi("1\n1 0 4 2352 \"Game (Track 1).bin\" 0\n").unwrap();
+        assert_eq!(files(&tracks), ["Game (Track 1).bin"]);
+    }
+
+    #[test]
+    fn reads_gdi_sheets_with_crlf_line_endings() {
+        let tracks = parse_gdi("1\r\n1 0 4 2352 track01.bin 0\r\n\r\n").unwrap();
+        assert_eq!(files(&tracks), ["track01.bin"]);
+    }
+
+    #[test]
+    fn rejects_gdi_sheets_with_the_wrong_track_count() {
+        let err = parse_gdi("2\n1 0 4 2352 track01.bin 0\n").unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+        assert!(err.to_string().contains("expected 2 tracks, found 1"));
+    }
+
+    #[test]
+    fn rejects_malformed_gdi_sheets() {
+        assert!(parse_gdi("").is_err());
+        assert!(parse_gdi("1\n1 0 4\n").is_err());
+    }
+
+    #[test]
+    fn splits_quoted_words() {
+        assert_eq!(split_quoted("a  b\tc"), ["a", "b", "c"]);
+        assert_eq!(split_quoted(r#"1 "two words" 3"#), ["1", "two words", "3"]);
+        assert_eq!(split_quoted(r#""""#), [""]);
+        assert_eq!(split_quoted(r#"a "unterminated"#), ["a", "unterminated"]);
+        assert!(split_quoted("   ").is_empty());
+    }
+}
@@ -0,0 +1,243 @@
+use crate::cache::DatCache;
+use crate::config::Config;
+use crate::error::{Result, ScanPatternErr};
+use glob::{MatchOptions, Pattern};
+use snafu::prelude::*;
+use std::collections::HashMap;
+use std::path::{Component, Path, PathBuf};
+
+/// Like a shell, `*` doesn't match across directories, so `*.txt` only matches files at the top
+/// level and `**/*.txt` matches them at any depth
+const MATCH_OPTIONS: MatchOptions = MatchOptions {
+    case_sensitive: true,
+    require_literal_separator: true,
+    require_literal_leading_dot: false,
+};
+
+/// Compiled include and exclude globs
+#[derive(Default)]
+struct Globs {
+    include: Vec<Pattern>,
+    exclude: Vec<Pattern>,
+}
+
+impl Globs {
+    fn new(include: &[String], exclude: &[String]) -> Result<Self> {
+        Ok(Self {
+            include: compile(include)?,
+            exclude: compile(exclude)?,
+        })
+    }
+
+    /// Excluded paths are always rejected. Include globs only apply to files, so that the
+    /// directories which contain included files are still descended into.
+    fn allows(&self, path: &str, is_dir: bool) -> bool {
+        let matches = |glob: &Pattern| glob.matches_with(path, MATCH_OPTIONS);
+        if self.exclude.iter().any(matches) {
+            return false;
+        }
+
+        is_dir || self.include.is_empty() || self.include.iter().any(matches)
+    }
+}
+
+/// Decides which paths of a collection are scanned, from the global include and exclude globs
+/// and those of each system. Global globs are relative to the collection, and system globs are
+/// relative to the system's directory. The database cache is never scanned.
+pub struct PathFilter {
+    root: PathBuf,
+    cache_dir: PathBuf,
+    global: Globs,
+    systems: HashMap<String, Globs>,
+}
+
+impl PathFilter {
+    pub fn new<P: Into<PathBuf>>(root: P, config: &Config) -> Result<Self> {
+        let (include, exclude) = config.scan_patterns();
+        let global = Globs::new(include, exclude)?;
+
+        let mut systems = HashMap::new();
+        for system in config.systems() {
+            let (include, exclude) = config.system_scan_patterns(system);
+            systems.insert(system.to_owned(), Globs::new(include, exclude)?);
+        }
+
+        let root = root.into();
+        let cache_dir = DatCache::for_db_dir(root.join(config.db_dir()))
+            .dir()
+            .to_path_buf();
+
+        Ok(Self {
+            root,
+            cache_dir,
+            global,
+            systems,
+        })
+    }
+
+    pub fn allows(&self, path: &Path, is_dir: bool) -> bool {
+        if path.starts_with(&self.cache_dir) {
+            return false;
+        }
+
+        let Ok(relative) = path.strip_prefix(&self.root) else {
+            return true;
+        };
+
+        if !self.global.allows(&to_slashes(relative), is_dir) {
+            return false;
+        }
+
+        let mut components = relative.components();
+        let system = match components.next() {
+            Some(Component::Normal(system)) => system.to_str().and_then(|s| self.systems.get(s)),
+            _ => None,
+        };
+
+        match system {
+            Some(globs) if components.as_path() != Path::new("") => {
+                globs.allows(&to_slashes(components.as_path()), is_dir)
+            }
+            _ => true,
+        }
+    }
+}
+
+fn compile(patterns: &[String]) -> Result<Vec<Pattern>> {
+    patterns
+        .iter()
+        .map(|pattern| Pattern::new(pattern).context(ScanPatternErr { pattern }))
+        .collect()
+}
+
+/// Globs are written with forward slashes, whatever the platform
+fn to_slashes(path: &Path) -> String {
+    path.iter()
+        .map(|c| c.to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn filter(config: &str) -> PathFilter {
+        let config: Config = toml::from_str(config).unwrap();
+        PathFilter::new("/roms", &config).unwrap()
+    }
+
+    fn allows(filter: &PathFilter, path: &str) -> bool {
+        filter.allows(&Path::new("/roms").join(path), false)
+    }
+
+    #[test]
+    fn allows_everything_without_patterns() {
+        let filter = filter(
+            r#"
+            [global]
+            db_dir = "dats"
+
+            [system]
+            "#,
+        );
+        assert!(allows(&filter, "gb/Game.zip"));
+    }
+
+    #[test]
+    fn excludes_global_patterns() {
+        let filter = filter(
+            r#"
+            [global]
+            db_dir = "dats"
+            exclude = ["*.txt", "**/*.nfo"]
+
+            [system]
+            "#,
+        );
+        assert!(!allows(&filter, "notes.txt"));
+        assert!(allows(&filter, "gb/notes.txt"));
+        assert!(!allows(&filter, "info.nfo"));
+        assert!(!allows(&filter, "gb/sub/info.nfo"));
+    }
+
+    #[test]
+    fn excludes_directories() {
+        let filter = filter(
+            r#"
+            [global]
+            db_dir = "dats"
+            exclude = "gb/homebrew"
+
+            [system]
+            "#,
+        );
+        assert!(!filter.allows(Path::new("/roms/gb/homebrew"), true));
+        assert!(filter.allows(Path::new("/roms/gb/other"), true));
+    }
+
+    #[test]
+    fn includes_only_matching_files() {
+        let filter = filter(
+            r#"
+            [global]
+            db_dir = "dats"
+            include = "*/*.zip"
+
+            [system]
+            "#,
+        );
+        assert!(allows(&filter, "gb/Game.zip"));
+        assert!(!allows(&filter, "gb/Game.7z"));
+        assert!(!allows(&filter, "gb/sub/Game.zip"));
+        assert!(filter.allows(Path::new("/roms/gb/sub"), true));
+    }
+
+    #[test]
+    fn applies_system_patterns_inside_the_system() {
+        let filter = filter(
+            r#"
+            [global]
+            db_dir = "dats"
+
+            [system.gb]
+            archive_format = "zip"
+            raw_format = "gb"
+            exclude = "*.gb"
+            "#,
+        );
+        assert!(!allows(&filter, "gb/Game.gb"));
+        assert!(allows(&filter, "gb/sub/Game.gb"));
+        assert!(allows(&filter, "gba/Game.gb"));
+        assert!(allows(&filter, "Game.gb"));
+    }
+
+    #[test]
+    fn excludes_the_database_cache() {
+        let filter = filter(
+            r#"
+            [global]
+            db_dir = "dats"
+
+            [system]
+            "#,
+        );
+        assert!(!filter.allows(Path::new("/roms/.romlint-cache"), true));
+        assert!(!allows(&filter, ".romlint-cache/gb.dat-0123abcd.bin"));
+        assert!(allows(&filter, "dats/gb.dat"));
+    }
+
+    #[test]
+    fn ignores_paths_outside_the_collection() {
+        let filter = filter(
+            r#"
+            [global]
+            db_dir = "dats"
+            exclude = "*"
+
+            [system]
+            "#,
+        );
+        assert!(filter.allows(Path::new("/elsewhere/Game.zip"), false));
+    }
+}
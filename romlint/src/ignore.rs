@@ -0,0 +1,313 @@
+use crate::error::{IgnorePatternErr, IoErr, Result};
+use glob::{MatchOptions, Pattern};
+use snafu::prelude::*;
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// The name of the files which list paths to ignore, in any directory of the collection
+pub const IGNORE_FILE: &str = ".romlintignore";
+
+const MATCH_OPTIONS: MatchOptions = MatchOptions {
+    case_sensitive: true,
+    require_literal_separator: true,
+    require_literal_leading_dot: false,
+};
+
+/// A single line of an ignore file. Like `.gitignore`, a pattern without a slash matches a file
+/// or directory name at any depth, one with a slash matches a path relative to the ignore file,
+/// and a trailing slash only matches directories. A pattern starting with `!` un-ignores paths
+/// which an earlier rule ignored, and the last rule to match a path decides. Glob characters can
+/// be escaped with a backslash, which is needed for names like `Game [b].zip`, and so can a
+/// leading `!` or `#`.
+///
+/// A pattern can be followed by `::` and a comma-separated list of lints, in which case only
+/// those lints are ignored for the matching files:
+///
+/// ```text
+/// # A translation we want to keep
+/// Game (Japan) \[T-En\].zip
+/// Arcade Game (World).zip :: multifile_archive, uncompressed_file
+/// homebrew/
+/// !homebrew/Keeper (World).zip
+/// ```
+struct Rule {
+    pattern: Pattern,
+    anchored: bool,
+    dir_only: bool,
+    negated: bool,
+    lints: Option<Vec<String>>,
+}
+
+impl Rule {
+    fn parse(line: &str) -> std::result::Result<Option<Self>, glob::PatternError> {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            return Ok(None);
+        }
+
+        let (pattern, lints) = match line.rsplit_once("::") {
+            Some((pattern, lints)) if !pattern.ends_with('\\') && is_lint_list(lints) => {
+                let lints = lints.split(',').map(|lint| lint.trim().to_owned());
+                (pattern.trim_end(), Some(lints.collect()))
+            }
+            _ => (line, None),
+        };
+
+        let (pattern, negated) = match pattern.strip_prefix('!') {
+            Some(pattern) => (pattern, true),
+            None => (pattern, false),
+        };
+
+        let dir_only = pattern.ends_with('/');
+        let pattern = pattern.trim_end_matches('/');
+        let anchored = pattern.contains('/');
+        let pattern = pattern.trim_start_matches('/');
+
+        Ok(Some(Self {
+            pattern: Pattern::new(&unescape(pattern))?,
+            anchored,
+            dir_only,
+            negated,
+            lints,
+        }))
+    }
+
+    /// Whether the rule matches a path relative to the directory of its ignore file
+    fn matches(&self, path: &Path) -> bool {
+        let components = path.iter().filter_map(|c| c.to_str()).collect::<Vec<_>>();
+        let len = if self.dir_only {
+            components.len().saturating_sub(1)
+        } else {
+            components.len()
+        };
+
+        if self.anchored {
+            (1..=len).any(|i| {
+                let prefix = components[..i].join("/");
+                self.pattern.matches_with(&prefix, MATCH_OPTIONS)
+            })
+        } else {
+            components[..len]
+                .iter()
+                .any(|name| self.pattern.matches_with(name, MATCH_OPTIONS))
+        }
+    }
+
+    /// Whether the rule applies when checking for a lint, or for the whole file when `lint` is None
+    fn applies_to(&self, lint: Option<&str>) -> bool {
+        match (&self.lints, lint) {
+            (None, _) => true,
+            (Some(lints), Some(lint)) => lints.iter().any(|l| l == lint),
+            (Some(_), None) => false,
+        }
+    }
+}
+
+/// The ignore files of a collection, which are read as they are needed. Rules apply to the
+/// directory their ignore file is in, and everything below it.
+pub struct Ignores {
+    root: PathBuf,
+    dirs: HashMap<PathBuf, Vec<Rule>>,
+}
+
+impl Ignores {
+    pub fn new<P: Into<PathBuf>>(root: P) -> Self {
+        Self {
+            root: root.into(),
+            dirs: HashMap::new(),
+        }
+    }
+
+    /// Whether a file should be skipped entirely
+    pub fn is_ignored(&mut self, path: &Path) -> Result<bool> {
+        self.check(path, None)
+    }
+
+    /// Whether a single lint should be skipped for a file
+    pub fn is_lint_ignored(&mut self, path: &Path, lint: &str) -> Result<bool> {
+        self.check(path, Some(lint))
+    }
+
+    /// Rules in deeper ignore files come after those in their parents, so that they can override
+    /// them
+    fn check(&mut self, path: &Path, lint: Option<&str>) -> Result<bool> {
+        let mut dirs = path
+            .ancestors()
+            .skip(1)
+            .take_while(|dir| dir.starts_with(&self.root))
+            .map(|dir| dir.to_path_buf())
+            .collect::<Vec<_>>();
+        dirs.reverse();
+
+        let mut ignored = false;
+        for dir in dirs {
+            let relative = path.strip_prefix(&dir).unwrap_or(path);
+            if let Some(decision) = last_match(self.rules(dir)?, relative, lint) {
+                ignored = decision;
+            }
+        }
+
+        Ok(ignored)
+    }
+
+    fn rules(&mut self, dir: PathBuf) -> Result<&[Rule]> {
+        if !self.dirs.contains_key(&dir) {
+            let rules = read_rules(&dir.join(IGNORE_FILE))?;
+            self.dirs.insert(dir.clone(), rules);
+        }
+
+        Ok(&self.dirs[&dir])
+    }
+}
+
+fn read_rules(path: &Path) -> Result<Vec<Rule>> {
+    let s = match fs::read_to_string(path) {
+        Ok(s) => s,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(err) => return Err(err).context(IoErr { path }),
+    };
+
+    let mut rules = Vec::new();
+    for (i, line) in s.lines().enumerate() {
+        let rule = Rule::parse(line).context(IgnorePatternErr { path, line: i + 1 })?;
+        rules.extend(rule);
+    }
+
+    Ok(rules)
+}
+
+/// Whether the last of `rules` to match a path ignores it, or None when none of them match
+fn last_match(rules: &[Rule], path: &Path, lint: Option<&str>) -> Option<bool> {
+    rules
+        .iter()
+        .rev()
+        .find(|rule| rule.applies_to(lint) && rule.matches(path))
+        .map(|rule| !rule.negated)
+}
+
+fn is_lint_list(s: &str) -> bool {
+    !s.trim().is_empty()
+        && s.chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == ',' || c == ' ')
+}
+
+/// Turn backslash escapes into the bracketed form which `glob` understands
+fn unescape(pattern: &str) -> String {
+    let mut unescaped = String::with_capacity(pattern.len());
+    let mut chars = pattern.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some(escaped) => unescaped.push_str(&Pattern::escape(&escaped.to_string())),
+                None => unescaped.push('\\'),
+            },
+            c => unescaped.push(c),
+        }
+    }
+
+    unescaped
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn rules(lines: &[&str]) -> Vec<Rule> {
+        lines
+            .iter()
+            .filter_map(|line| Rule::parse(line).unwrap())
+            .collect()
+    }
+
+    fn ignored(rules: &[Rule], path: &str) -> bool {
+        last_match(rules, Path::new(path), None).unwrap_or(false)
+    }
+
+    fn lint_ignored(rules: &[Rule], path: &str, lint: &str) -> bool {
+        last_match(rules, Path::new(path), Some(lint)).unwrap_or(false)
+    }
+
+    #[test]
+    fn skips_comments_and_blank_lines() {
+        assert!(rules(&["", "  ", "# comment"]).is_empty());
+    }
+
+    #[test]
+    fn matches_names_at_any_depth() {
+        let rules = rules(&["*.txt"]);
+        assert!(ignored(&rules, "notes.txt"));
+        assert!(ignored(&rules, "gb/notes.txt"));
+        assert!(!ignored(&rules, "gb/Game.zip"));
+    }
+
+    #[test]
+    fn anchors_patterns_with_slashes() {
+        let rules = rules(&["gb/*.txt"]);
+        assert!(ignored(&rules, "gb/notes.txt"));
+        assert!(!ignored(&rules, "nes/gb/notes.txt"));
+        assert!(!ignored(&rules, "gb/sub/notes.txt"));
+    }
+
+    #[test]
+    fn matches_only_directories_with_trailing_slash() {
+        let rules = rules(&["homebrew/"]);
+        assert!(ignored(&rules, "homebrew/Game.zip"));
+        assert!(!ignored(&rules, "homebrew"));
+    }
+
+    #[test]
+    fn escapes_glob_characters() {
+        let rules = rules(&["Game \\[b\\].zip"]);
+        assert!(ignored(&rules, "Game [b].zip"));
+        assert!(!ignored(&rules, "Game b.zip"));
+    }
+
+    #[test]
+    fn reads_names_with_colons_as_patterns() {
+        let rules = rules(&["Star Wars: Episode I*"]);
+        assert!(rules[0].lints.is_none());
+        assert!(ignored(&rules, "Star Wars: Episode I (USA).zip"));
+    }
+
+    #[test]
+    fn limits_rules_to_lints() {
+        let rules = rules(&["Game.zip :: multifile_archive, uncompressed_file"]);
+        assert!(!ignored(&rules, "Game.zip"));
+        assert!(lint_ignored(&rules, "Game.zip", "multifile_archive"));
+        assert!(lint_ignored(&rules, "Game.zip", "uncompressed_file"));
+        assert!(!lint_ignored(&rules, "Game.zip", "unknown_file"));
+    }
+
+    #[test]
+    fn escapes_lint_separator() {
+        let rules = rules(&["Odd\\:: name"]);
+        assert!(rules[0].lints.is_none());
+    }
+
+    #[test]
+    fn negates_earlier_rules() {
+        let rules = rules(&["homebrew/", "!homebrew/Keeper.zip"]);
+        assert!(ignored(&rules, "homebrew/Other.zip"));
+        assert!(!ignored(&rules, "homebrew/Keeper.zip"));
+    }
+
+    #[test]
+    fn lets_later_rules_win() {
+        let rules = rules(&["!Keeper.zip", "*.zip"]);
+        assert!(ignored(&rules, "Keeper.zip"));
+    }
+
+    #[test]
+    fn escapes_leading_exclamation_marks() {
+        let rules = rules(&["\\!Bang.zip"]);
+        assert!(ignored(&rules, "!Bang.zip"));
+    }
+
+    #[test]
+    fn rejects_invalid_patterns() {
+        assert!(Rule::parse("Game [b.zip").is_err());
+    }
+}
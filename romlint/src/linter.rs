@@ -1,9 +1,11 @@
 use crate::filemeta::FileMeta;
 use serde::Serialize;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 #[derive(Debug, Serialize)]
 pub struct Diagnostic {
+    /// The name of the lint which produced this diagnostic
+    pub lint: Option<String>,
     pub message: String,
     pub path: PathBuf,
     pub hints: Option<Vec<String>>,
@@ -11,12 +13,22 @@ pub struct Diagnostic {
 }
 
 impl Diagnostic {
-    pub fn from_file<M: Into<String>>(file: &FileMeta, message: M) -> Self {
+    pub fn new<M: Into<String>>(path: &Path, message: M) -> Self {
         Self {
+            lint: None,
             hints: None,
             message: message.into(),
-            path: file.path().to_path_buf(),
+            path: path.to_path_buf(),
             terminal: false,
         }
     }
+
+    pub fn from_file<M: Into<String>>(file: &FileMeta, message: M) -> Self {
+        Self::new(file.path(), message)
+    }
+
+    pub fn with_lint<S: Into<String>>(mut self, lint: S) -> Self {
+        self.lint = Some(lint.into());
+        self
+    }
 }
@@ -1,10 +1,16 @@
 mod ansi;
 mod args;
+mod baseline;
+mod cache;
+mod checksum;
 mod commands;
 mod config;
 mod db;
+mod duplicates;
 mod error;
 mod filemeta;
+mod filter;
+mod ignore;
 mod linter;
 mod scripts;
 mod ui;
@@ -12,7 +18,7 @@ mod word_match;
 
 use args::{Args, Command};
 use clap::Parser;
-use commands::{dump, lint};
+use commands::{dat_check, dat_create, dat_diff, dump, dupes, lint, m3u, missing, sync_names};
 use error::Result;
 
 #[tokio::main(flavor = "current_thread")]
@@ -21,8 +27,15 @@ async fn main() {
 
     let args = Args::parse();
     let res = match args.command {
-        Command::Dump => dump(args).await,
+        Command::Dump(ref dump_args) => dump(&args, dump_args).await,
+        Command::DatCreate(ref create_args) => dat_create(&args, create_args).await,
+        Command::DatDiff(ref diff_args) => dat_diff(&args, diff_args).await,
+        Command::DatCheck(ref check_args) => dat_check(&args, check_args),
+        Command::Missing(ref missing_args) => missing(&args, missing_args).await,
+        Command::Dupes(ref dupes_args) => dupes(&args, dupes_args).await,
+        Command::M3u(ref m3u_args) => m3u(&args, m3u_args).await,
         Command::Lint(ref lint_args) => lint(&args, lint_args).await,
+        Command::SyncNames(ref sync_args) => sync_names(&args, sync_args).await,
     };
 
     if let Err(err) = res {
@@ -1,11 +1,18 @@
 use crate::{
-    db::Databases,
-    filemeta::{ArchiveInfo, FileMeta},
+    checksum,
+    config::Config,
+    db::{Database, Databases, Entry, RomEntry},
+    duplicates,
+    filemeta::{ArchiveInfo, FileMeta, LinkedFile, ListingEntry, Playlist, Sheet},
     word_match::Tokens,
 };
 use bitflags::bitflags;
+use dat::{Crc32, ForceNoDump, ParseHashError, Rom};
 use futures::io;
-use mlua::{Function, IntoLua, Lua, Result, StdLib, Value};
+use mlua::{Function, IntoLua, Lua, Result, StdLib, Table, Value};
+use std::cell::RefCell;
+use std::fs::File;
+use std::io::BufReader;
 use std::{fs::Metadata, os::unix::prelude::MetadataExt, path::Path as FsPath, sync::Arc};
 use tokio::fs::read_to_string;
 
@@ -27,14 +34,19 @@ impl ScriptLoader {
             .and_then(|s| s.to_str())
             .unwrap_or("")
             .to_owned();
-        let requirements = Self::get_requirements(&src, &name).unwrap();
+        let meta = Self::get_metadata(&src, &name).unwrap();
 
         log::debug!("Loading script: {name}");
 
         self.scripts.push(Script {
-            requirements,
+            requirements: meta.requirements,
             src,
             name,
+            description: meta.description,
+            help: meta.help,
+            lints_files: meta.lints_files,
+            lints_dirs: meta.lints_dirs,
+            lints_collection: meta.lints_collection,
         });
 
         Ok(())
@@ -46,10 +58,28 @@ impl ScriptLoader {
             .fold(Requirements::empty(), |acc, x| acc | x.requirements)
     }
 
-    fn get_requirements(src: &str, name: &str) -> Result<Requirements> {
+    /// Read the globals which describe a script: its requirements, optionally a short
+    /// `description` and longer `help` text for reporters which list each lint, and whether it
+    /// lints files with `lint`, directories with `lint_dir`, or a whole system at once with
+    /// `lint_collection`
+    fn get_metadata(src: &str, name: &str) -> Result<ScriptMeta> {
         let lua = Lua::new_with(StdLib::TABLE | StdLib::STRING, Default::default())?;
         lua.load(src).set_name(name).exec()?;
 
+        let lints_files = lua
+            .globals()
+            .get::<&str, Option<Function>>("lint")?
+            .is_some();
+        let lints_dirs = lua
+            .globals()
+            .get::<&str, Option<Function>>("lint_dir")?
+            .is_some();
+        let lints_collection = lua
+            .globals()
+            .get::<&str, Option<Function>>("lint_collection")?
+            .is_some();
+        let description = lua.globals().get::<&str, Option<String>>("description")?;
+        let help = lua.globals().get::<&str, Option<String>>("help")?;
         let reqs = lua
             .globals()
             .get::<&str, Vec<String>>("requires")?
@@ -59,13 +89,22 @@ impl ScriptLoader {
                 "path" => acc | Requirements::PATH,
                 "archive" => acc | Requirements::ARCHIVE,
                 "file_db" => acc | Requirements::FILE_DB,
+                "cue" => acc | Requirements::CUE,
+                "playlist" => acc | Requirements::PLAYLIST,
                 s => {
                     log::warn!("Unknown requirement listed: '{s}'");
                     acc
                 }
             });
 
-        Ok(reqs)
+        Ok(ScriptMeta {
+            requirements: reqs,
+            description,
+            help,
+            lints_files,
+            lints_dirs,
+            lints_collection,
+        })
     }
 
     pub fn iter(&self) -> impl Iterator<Item = &Script> {
@@ -73,13 +112,24 @@ impl ScriptLoader {
     }
 }
 
+struct ScriptMeta {
+    requirements: Requirements,
+    description: Option<String>,
+    help: Option<String>,
+    lints_files: bool,
+    lints_dirs: bool,
+    lints_collection: bool,
+}
+
 bitflags! {
     #[derive(Clone, Copy, Debug, PartialEq, Eq)]
     pub struct Requirements: u32 {
-        const PATH    = 0b0001;
-        const STAT    = 0b0010;
-        const ARCHIVE = 0b0100;
-        const FILE_DB = 0b1000;
+        const PATH     = 0b000001;
+        const STAT     = 0b000010;
+        const ARCHIVE  = 0b000100;
+        const FILE_DB  = 0b001000;
+        const CUE      = 0b010000;
+        const PLAYLIST = 0b100000;
     }
 }
 
@@ -90,6 +140,8 @@ impl Requirements {
             Self::STAT => "stat",
             Self::ARCHIVE => "archive",
             Self::FILE_DB => "file_db",
+            Self::CUE => "cue",
+            Self::PLAYLIST => "playlist",
             _ => "multiple requirements",
         }
     }
@@ -116,6 +168,42 @@ pub struct Script {
     requirements: Requirements,
     src: String,
     name: String,
+    description: Option<String>,
+    help: Option<String>,
+    lints_files: bool,
+    lints_dirs: bool,
+    lints_collection: bool,
+}
+
+impl Script {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn description(&self) -> Option<&str> {
+        self.description.as_deref()
+    }
+
+    pub fn help(&self) -> Option<&str> {
+        self.help.as_deref()
+    }
+
+    /// Whether the script defines `lint`, which is run for every file and directory
+    pub fn lints_files(&self) -> bool {
+        self.lints_files
+    }
+
+    /// Whether the script defines `lint_dir`, which is run for directories along with a listing
+    /// of their contents
+    pub fn lints_dirs(&self) -> bool {
+        self.lints_dirs
+    }
+
+    /// Whether the script defines `lint_collection`, which is run once per system with every file
+    /// in it
+    pub fn lints_collection(&self) -> bool {
+        self.lints_collection
+    }
 }
 
 impl std::fmt::Debug for Script {
@@ -132,58 +220,7 @@ pub fn exec_one(script: &Script, meta: &FileMeta, databases: Arc<Databases>) ->
         let file = lua.create_table()?;
         let api = lua.create_table()?;
 
-        let assert_eq = lua.create_function(
-            |_, (expected, actual, detail): (Value, Value, Option<String>)| {
-                if lua_eq(&expected, &actual) {
-                    return Ok(());
-                }
-
-                let err = AssertionError::expected(&expected, &actual, detail);
-                let err = mlua::Error::ExternalError(Arc::new(err));
-                Err(err)
-            },
-        )?;
-
-        let assert_ne = lua.create_function(
-            |_, (not_expected, actual, detail): (Value, Value, Option<String>)| {
-                if lua_eq(&not_expected, &actual) {
-                    let err = AssertionError::unexpected(&not_expected, detail);
-                    let err = mlua::Error::ExternalError(Arc::new(err));
-                    Err(err)?
-                }
-
-                Ok(())
-            },
-        )?;
-
-        let throw = lua.create_function(|_, detail: String| -> Result<()> {
-            let err = AssertionError::throw(detail);
-            let err = mlua::Error::ExternalError(Arc::new(err));
-            Err(err)
-        })?;
-
-        let assert_contains = lua.create_function(
-            |_, (haystack, needle, detail): (Vec<Value>, Value, Option<String>)| {
-                for item in haystack {
-                    if lua_eq(&item, &needle) {
-                        return Ok(());
-                    }
-                }
-
-                let detail = detail.unwrap_or_else(|| {
-                    format!("Couldn't find '{}' in collection", fmt_lua(&needle),)
-                });
-
-                let err = AssertionError::with_message(detail);
-                let err = mlua::Error::ExternalError(Arc::new(err));
-                Err(err)
-            },
-        )?;
-
-        api.set("assert_eq", assert_eq)?;
-        api.set("assert_ne", assert_ne)?;
-        api.set("assert_contains", assert_contains)?;
-        api.set("throw", throw)?;
+        add_assertions(&lua, &api)?;
 
         api.set("system", meta.system())?;
         api.set("config", meta.config())?;
@@ -239,6 +276,67 @@ pub fn exec_one(script: &Script, meta: &FileMeta, databases: Arc<Databases>) ->
 
         api.set("similar_files", similar_files)?;
 
+        let databases = &databases;
+        let lookup = |by: DbLookup| {
+            scope.create_function(move |_, key: String| {
+                if !script.requirements.contains(Requirements::FILE_DB) {
+                    let err = RequirementError::new(Requirements::FILE_DB);
+                    let err = mlua::Error::ExternalError(Arc::new(err));
+                    Err(err)?;
+                }
+
+                let game = meta
+                    .system()
+                    .and_then(|sys| databases.as_ref().get(sys))
+                    .map(|db| by(db, key.as_str()))
+                    .transpose()
+                    .map_err(mlua::Error::external)?
+                    .flatten()
+                    .map(DbGame::from);
+
+                Ok(game)
+            })
+        };
+
+        api.set("db_by_name", lookup(|db, name| Ok(db.by_name(name)))?)?;
+        api.set(
+            "db_by_crc",
+            lookup(|db, crc| Ok(first_game(db.by_crc(&crc.parse()?))))?,
+        )?;
+        api.set(
+            "db_by_md5",
+            lookup(|db, md5| Ok(first_game(db.by_md5(&md5.parse()?))))?,
+        )?;
+        api.set(
+            "db_by_sha1",
+            lookup(|db, sha1| Ok(first_game(db.by_sha1(&sha1.parse()?))))?,
+        )?;
+        api.set(
+            "db_by_sha256",
+            lookup(|db, sha256| Ok(first_game(db.by_sha256(&sha256.parse()?))))?,
+        )?;
+
+        let db_alternatives = scope.create_function(|_, name: String| {
+            if !script.requirements.contains(Requirements::FILE_DB) {
+                let err = RequirementError::new(Requirements::FILE_DB);
+                let err = mlua::Error::ExternalError(Arc::new(err));
+                Err(err)?;
+            }
+
+            let games = meta
+                .system()
+                .and_then(|sys| databases.as_ref().get(sys))
+                .map(|db| db.alternatives(&name))
+                .unwrap_or_default()
+                .into_iter()
+                .map(DbGame::from)
+                .collect::<Vec<_>>();
+
+            Ok(games)
+        })?;
+
+        api.set("db_alternatives", db_alternatives)?;
+
         let stat = scope.create_function(|_, ()| {
             if !script.requirements.contains(Requirements::STAT) {
                 let err = RequirementError::new(Requirements::STAT);
@@ -278,11 +376,276 @@ pub fn exec_one(script: &Script, meta: &FileMeta, databases: Arc<Databases>) ->
 
         file.set("archive", archive)?;
 
+        // Named after the far more common format, but GDI sheets are read too. Directory listings
+        // are read up front, so their entries have a plain `sheet` field instead.
+        let cue = scope.create_function(|_, ()| {
+            if !script.requirements.contains(Requirements::CUE) {
+                let err = RequirementError::new(Requirements::CUE);
+                let err = mlua::Error::ExternalError(Arc::new(err));
+                Err(err)?;
+            }
+
+            meta.sheet().map_err(mlua::Error::external)
+        })?;
+
+        file.set("cue", cue)?;
+
+        let m3u = scope.create_function(|_, ()| {
+            if !script.requirements.contains(Requirements::PLAYLIST) {
+                let err = RequirementError::new(Requirements::PLAYLIST);
+                let err = mlua::Error::ExternalError(Arc::new(err));
+                Err(err)?;
+            }
+
+            meta.playlist().map_err(mlua::Error::external)
+        })?;
+
+        file.set("m3u", m3u)?;
+
         lua.load(&script.src).set_name(&script.name).exec()?;
         globals.get::<&str, Function>("lint")?.call((file, api))
     })
 }
 
+/// The functions lints use to report problems, which are the same for every kind of lint
+fn add_assertions<'lua>(lua: &'lua Lua, api: &Table<'lua>) -> Result<()> {
+    let assert_eq = lua.create_function(
+        |_, (expected, actual, detail): (Value, Value, Option<String>)| {
+            if lua_eq(&expected, &actual) {
+                return Ok(());
+            }
+
+            let err = AssertionError::expected(&expected, &actual, detail);
+            let err = mlua::Error::ExternalError(Arc::new(err));
+            Err(err)
+        },
+    )?;
+
+    let assert_ne = lua.create_function(
+        |_, (not_expected, actual, detail): (Value, Value, Option<String>)| {
+            if lua_eq(&not_expected, &actual) {
+                let err = AssertionError::unexpected(&not_expected, detail);
+                let err = mlua::Error::ExternalError(Arc::new(err));
+                Err(err)?
+            }
+
+            Ok(())
+        },
+    )?;
+
+    let throw = lua.create_function(|_, detail: String| -> Result<()> {
+        let err = AssertionError::throw(detail);
+        let err = mlua::Error::ExternalError(Arc::new(err));
+        Err(err)
+    })?;
+
+    let assert_contains = lua.create_function(
+        |_, (haystack, needle, detail): (Vec<Value>, Value, Option<String>)| {
+            for item in haystack {
+                if lua_eq(&item, &needle) {
+                    return Ok(());
+                }
+            }
+
+            let detail = detail
+                .unwrap_or_else(|| format!("Couldn't find '{}' in collection", fmt_lua(&needle),));
+
+            let err = AssertionError::with_message(detail);
+            let err = mlua::Error::ExternalError(Arc::new(err));
+            Err(err)
+        },
+    )?;
+
+    api.set("assert_eq", assert_eq)?;
+    api.set("assert_ne", assert_ne)?;
+    api.set("assert_contains", assert_contains)?;
+    api.set("throw", throw)?;
+
+    Ok(())
+}
+
+/// Run a script's `lint_dir` function against a directory and the entries inside of it
+pub fn exec_dir(
+    script: &Script,
+    meta: &FileMeta,
+    listing: &[ListingEntry],
+    config: &Config,
+) -> Result<()> {
+    let lua = Lua::new_with(StdLib::TABLE | StdLib::STRING, Default::default())?;
+    let dir = lua.create_table()?;
+    let api = lua.create_table()?;
+
+    add_assertions(&lua, &api)?;
+
+    let mut systems = config.systems().collect::<Vec<_>>();
+    systems.sort_unstable();
+
+    api.set("system", meta.system())?;
+    api.set("systems", systems)?;
+    api.set("config", meta.config())?;
+
+    let path: Path = meta.path().into();
+    dir.set("path", path)?;
+    dir.set("entries", listing.iter().collect::<Vec<_>>())?;
+    dir.set(
+        "file_count",
+        listing.iter().filter(|entry| entry.is_file).count(),
+    )?;
+    dir.set(
+        "dir_count",
+        listing.iter().filter(|entry| entry.is_dir).count(),
+    )?;
+    dir.set("size", listing.iter().map(|entry| entry.size).sum::<u64>())?;
+
+    lua.load(&script.src).set_name(&script.name).exec()?;
+    let lint_dir = lua.globals().get::<&str, Function>("lint_dir")?;
+    lint_dir.call((dir, api))
+}
+
+/// A file as given to collection lints
+pub struct CollectionFile {
+    /// Relative to the collection, which is how collection lints refer to files
+    pub path: String,
+    pub size: u64,
+    pub archive_files: Option<Vec<String>>,
+}
+
+impl<'lua> IntoLua<'lua> for &CollectionFile {
+    fn into_lua(self, lua: &'lua mlua::Lua) -> Result<Value<'lua>> {
+        let path = FsPath::new(&self.path);
+        let table = lua.create_table()?;
+        table.set("path", self.path.as_str())?;
+        table.set("name", path.file_name().and_then(|name| name.to_str()))?;
+        table.set("stem", path.file_stem().and_then(|stem| stem.to_str()))?;
+        table.set("extension", path.extension().and_then(|ext| ext.to_str()))?;
+        table.set("size", self.size)?;
+        table.set("archive_files", self.archive_files.clone())?;
+
+        Ok(Value::Table(table))
+    }
+}
+
+/// Run a script's `lint_collection` function against every file of a system. Problems are
+/// reported with `api.report(path, message)`, and returned as pairs of the path and message.
+/// `api.crc32(path)` hashes a whole file, and `api.contents(path)` gives the size and CRC of each
+/// member of a zip, or of the whole file otherwise.
+pub fn exec_collection(
+    script: &Script,
+    system: &str,
+    files: &[CollectionFile],
+    config: &Config,
+    root: &FsPath,
+) -> Result<Vec<(String, String)>> {
+    let lua = Lua::new_with(StdLib::TABLE | StdLib::STRING, Default::default())?;
+    let reports = RefCell::new(Vec::new());
+
+    lua.scope(|scope| {
+        let api = lua.create_table()?;
+        add_assertions(&lua, &api)?;
+
+        api.set("system", system)?;
+        api.set("config", config.resolve(system).as_ref())?;
+
+        let report = scope.create_function(|_, (path, message): (String, String)| {
+            reports.borrow_mut().push((path, message));
+            Ok(())
+        })?;
+
+        api.set("report", report)?;
+
+        let crc32 = scope.create_function(|_, path: String| {
+            let file = File::open(root.join(&path)).map_err(mlua::Error::external)?;
+            let crc = checksum::crc32(BufReader::new(file)).map_err(mlua::Error::external)?;
+            Ok(Crc32::from(crc).to_string())
+        })?;
+
+        api.set("crc32", crc32)?;
+
+        let contents = scope.create_function(|lua, path: String| {
+            let contents =
+                duplicates::read_contents(&root.join(&path)).map_err(mlua::Error::external)?;
+            let table = lua.create_table()?;
+
+            for content in contents {
+                let entry = lua.create_table()?;
+                entry.set("member", content.member)?;
+                entry.set("size", content.size)?;
+                entry.set("crc", content.crc.to_string())?;
+                table.push(entry)?;
+            }
+
+            Ok(table)
+        })?;
+
+        api.set("contents", contents)?;
+
+        lua.load(&script.src).set_name(&script.name).exec()?;
+        let lint_collection = lua.globals().get::<&str, Function>("lint_collection")?;
+        lint_collection.call::<_, ()>((files.iter().collect::<Vec<_>>(), api))
+    })?;
+
+    Ok(reports.into_inner())
+}
+
+impl<'lua> IntoLua<'lua> for &ListingEntry {
+    fn into_lua(self, lua: &'lua mlua::Lua) -> Result<Value<'lua>> {
+        let table = lua.create_table()?;
+        table.set("name", self.name.as_str())?;
+        table.set("is_dir", self.is_dir)?;
+        table.set("is_file", self.is_file)?;
+        table.set("size", self.size)?;
+        table.set("sheet", self.sheet.as_ref())?;
+
+        Ok(Value::Table(table))
+    }
+}
+
+/// Cue and GDI sheets, with the files they reference and the tracks inside those files
+impl<'lua> IntoLua<'lua> for &Sheet {
+    fn into_lua(self, lua: &'lua mlua::Lua) -> Result<Value<'lua>> {
+        let tracks = lua.create_table()?;
+        for track in &self.tracks {
+            let table = lua.create_table()?;
+            table.set("number", track.number)?;
+            table.set("mode", track.mode.as_str())?;
+            table.set("file", track.file.as_str())?;
+            tracks.push(table)?;
+        }
+
+        let table = lua.create_table()?;
+        table.set("format", self.format.as_str())?;
+        table.set("files", self.files.iter().collect::<Vec<_>>())?;
+        table.set("tracks", tracks)?;
+
+        Ok(Value::Table(table))
+    }
+}
+
+impl<'lua> IntoLua<'lua> for Sheet {
+    fn into_lua(self, lua: &'lua mlua::Lua) -> Result<Value<'lua>> {
+        (&self).into_lua(lua)
+    }
+}
+
+impl<'lua> IntoLua<'lua> for Playlist {
+    fn into_lua(self, lua: &'lua mlua::Lua) -> Result<Value<'lua>> {
+        let table = lua.create_table()?;
+        table.set("entries", self.entries.iter().collect::<Vec<_>>())?;
+
+        Ok(Value::Table(table))
+    }
+}
+
+impl<'lua> IntoLua<'lua> for &LinkedFile {
+    fn into_lua(self, lua: &'lua mlua::Lua) -> Result<Value<'lua>> {
+        let table = lua.create_table()?;
+        table.set("name", self.name.as_str())?;
+        table.set("exists", self.exists)?;
+
+        Ok(Value::Table(table))
+    }
+}
+
 struct Archive {
     files: Option<Vec<String>>,
 }
@@ -308,6 +671,95 @@ impl From<Option<&ArchiveInfo>> for Archive {
     }
 }
 
+/// Finds a game by name or hash. Hashes given by scripts are parsed first, which can fail.
+/// Hash lookups give scripts the first game with a matching ROM
+fn first_game(roms: Vec<RomEntry<'_>>) -> Option<Entry<'_>> {
+    roms.first().map(|rom| rom.entry)
+}
+
+type DbLookup =
+    for<'a, 'b> fn(&'a Database, &'b str) -> std::result::Result<Option<Entry<'a>>, ParseHashError>;
+
+struct DbGame {
+    name: String,
+    description: String,
+    roms: Vec<DbRom>,
+    source: String,
+    force_no_dump: Option<&'static str>,
+}
+
+impl<'lua> IntoLua<'lua> for DbGame {
+    fn into_lua(self, lua: &'lua mlua::Lua) -> Result<Value<'lua>> {
+        let table = lua.create_table()?;
+        table.set("name", self.name)?;
+        table.set("description", self.description)?;
+        table.set("rom", self.roms.first().cloned())?;
+        table.set("roms", self.roms)?;
+        table.set("source", self.source)?;
+        table.set("force_no_dump", self.force_no_dump)?;
+
+        Ok(Value::Table(table))
+    }
+}
+
+impl From<Entry<'_>> for DbGame {
+    fn from(value: Entry<'_>) -> Self {
+        let game = value.game;
+
+        Self {
+            name: game.name.clone(),
+            description: game.description.clone(),
+            roms: game.roms.iter().map(DbRom::from).collect(),
+            source: value.source_name(),
+            force_no_dump: value
+                .header
+                .clrmamepro
+                .force_no_dump
+                .map(ForceNoDump::as_str),
+        }
+    }
+}
+
+#[derive(Clone)]
+struct DbRom {
+    name: String,
+    size: usize,
+    crc: Option<String>,
+    md5: Option<String>,
+    sha1: Option<String>,
+    sha256: Option<String>,
+    status: Option<String>,
+}
+
+impl<'lua> IntoLua<'lua> for DbRom {
+    fn into_lua(self, lua: &'lua mlua::Lua) -> Result<Value<'lua>> {
+        let table = lua.create_table()?;
+        table.set("name", self.name)?;
+        table.set("size", self.size)?;
+        table.set("crc", self.crc)?;
+        table.set("md5", self.md5)?;
+        table.set("sha1", self.sha1)?;
+        table.set("sha256", self.sha256)?;
+        table.set("status", self.status)?;
+
+        Ok(Value::Table(table))
+    }
+}
+
+impl From<&Rom> for DbRom {
+    fn from(value: &Rom) -> Self {
+        Self {
+            name: value.name.clone(),
+            size: value.size,
+            crc: value.crc.map(|crc| crc.to_string()),
+            md5: value.md5.map(|md5| md5.to_string()),
+            sha1: value.sha1.map(|sha1| sha1.to_string()),
+            sha256: value.sha256.map(|sha256| sha256.to_string()),
+            status: value.status.clone(),
+        }
+    }
+}
+
 struct Path {
     extension: Option<String>,
     stem: Option<String>,
@@ -337,10 +789,7 @@ impl From<&FsPath> for Path {
                 .file_stem()
                 .and_then(|stem| stem.to_str())
                 .map(|stem| stem.to_string()),
-            path: value
-                .to_str()
-                .map(|p| p.to_string())
-                .unwrap_or_else(String::new),
+            path: value.to_str().map(|p| p.to_string()).unwrap_or_default(),
         }
     }
 }
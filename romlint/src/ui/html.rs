@@ -0,0 +1,215 @@
+use super::{escape, Message, Report, Reporter, Summary};
+use crate::error::{IoErr, Result};
+use snafu::prelude::*;
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::{self, Write};
+
+/// Lints don't have severities yet, so every diagnostic is an error
+const SEVERITY: &str = "error";
+
+const STYLE: &str = r#"
+body { font-family: system-ui, sans-serif; margin: 2em auto; max-width: 60em; color: #222; }
+table { border-collapse: collapse; margin-bottom: 2em; }
+th, td { padding: 0.3em 1em; text-align: right; border-bottom: 1px solid #ddd; }
+th:first-child, td:first-child { text-align: left; }
+tfoot td { font-weight: bold; }
+.pass { color: #2a7d2a; }
+.fail { color: #b22; }
+.filters { margin-bottom: 1em; }
+.filters label { margin-right: 1em; }
+details { margin: 0.3em 0; }
+summary { cursor: pointer; font-family: monospace; }
+li .lint { color: #666; font-family: monospace; margin-right: 0.5em; }
+li .hint { color: #666; display: block; margin-left: 1em; }
+"#;
+
+/// Show only the diagnostics which match the selected lint and severity, and hide files which
+/// are left without any
+const SCRIPT: &str = r#"
+function applyFilters() {
+  const lint = document.getElementById("lint").value;
+  const severity = document.getElementById("severity").value;
+
+  for (const file of document.querySelectorAll("details.file")) {
+    let shown = 0;
+    for (const item of file.querySelectorAll("li")) {
+      const visible = (!lint || item.dataset.lint === lint) &&
+        (!severity || item.dataset.severity === severity);
+      item.hidden = !visible;
+      shown += visible;
+    }
+    file.hidden = shown === 0;
+  }
+}
+
+document.getElementById("lint").addEventListener("change", applyFilters);
+document.getElementById("severity").addEventListener("change", applyFilters);
+"#;
+
+/// Writes a single, self-contained HTML page once linting has finished. It has a table of
+/// results per system, along with how much of each system's DATs is present, and a collapsible
+/// list of diagnostics for each failing file which can be filtered by lint and severity.
+pub struct HtmlReporter {
+    /// By path, since collection lints can report a file again after it was checked on its own
+    failures: BTreeMap<String, Report>,
+}
+
+impl HtmlReporter {
+    pub fn new() -> Self {
+        Self {
+            failures: BTreeMap::new(),
+        }
+    }
+
+    fn write<W: Write>(&self, w: &mut W, summary: &Summary) -> io::Result<()> {
+        writeln!(w, "<!DOCTYPE html>")?;
+        writeln!(w, r#"<html lang="en">"#)?;
+        writeln!(w, "<head>")?;
+        writeln!(w, r#"<meta charset="utf-8">"#)?;
+        writeln!(w, "<title>romlint report</title>")?;
+        writeln!(w, "<style>{STYLE}</style>")?;
+        writeln!(w, "</head>")?;
+        writeln!(w, "<body>")?;
+        writeln!(w, "<h1>romlint report</h1>")?;
+
+        write_summary(w, summary)?;
+        self.write_failures(w)?;
+
+        writeln!(w, "</body>")?;
+        writeln!(w, "</html>")?;
+        w.flush()
+    }
+
+    fn write_failures<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        writeln!(w, "<h2>Failures</h2>")?;
+
+        if self.failures.is_empty() {
+            return writeln!(w, r#"<p class="pass">Every file passed.</p>"#);
+        }
+
+        let lints = self
+            .failures
+            .values()
+            .flat_map(|report| &report.diagnostics)
+            .filter_map(|diagnostic| diagnostic.lint.as_deref())
+            .collect::<BTreeSet<_>>();
+
+        writeln!(w, r#"<div class="filters">"#)?;
+        writeln!(w, r#"<label>Lint <select id="lint">"#)?;
+        writeln!(w, r#"<option value="">All</option>"#)?;
+        for lint in lints {
+            writeln!(w, "<option>{}</option>", escape(lint))?;
+        }
+        writeln!(w, "</select></label>")?;
+        writeln!(w, r#"<label>Severity <select id="severity">"#)?;
+        writeln!(w, r#"<option value="">All</option>"#)?;
+        writeln!(w, "<option>{SEVERITY}</option>")?;
+        writeln!(w, "</select></label>")?;
+        writeln!(w, "</div>")?;
+
+        for report in self.failures.values() {
+            write_report(w, report)?;
+        }
+
+        writeln!(w, "<script>{SCRIPT}</script>")
+    }
+}
+
+impl Reporter for HtmlReporter {
+    fn on_tick(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    fn on_message(&mut self, message: Message) -> Result<()> {
+        match message {
+            Message::Report(report) if !report.ok() => match self.failures.get_mut(&report.path) {
+                Some(existing) => existing.diagnostics.extend(report.diagnostics),
+                None => {
+                    self.failures.insert(report.path.clone(), report);
+                }
+            },
+            Message::Finished(summary) => {
+                self.write(&mut io::stdout().lock(), &summary)
+                    .context(IoErr { path: "stdout" })?;
+            }
+            _ => {}
+        }
+
+        Ok(())
+    }
+}
+
+fn write_summary<W: Write>(w: &mut W, summary: &Summary) -> io::Result<()> {
+    let mut systems = summary.systems.iter().collect::<Vec<_>>();
+    systems.sort_unstable_by_key(|(system, _)| *system);
+
+    writeln!(w, "<h2>Summary</h2>")?;
+    writeln!(w, "<table>")?;
+    writeln!(
+        w,
+        "<thead><tr><th>System</th><th>Passed</th><th>Failed</th><th>Have</th><th>Missing</th></tr></thead>"
+    )?;
+    writeln!(w, "<tbody>")?;
+
+    for (system, system_summary) in systems {
+        let (have, missing) = match system_summary.collection {
+            Some(collection) => (collection.have.to_string(), collection.missing.to_string()),
+            None => ("-".to_owned(), "-".to_owned()),
+        };
+
+        writeln!(
+            w,
+            r#"<tr><td>{}</td><td class="pass">{}</td><td class="fail">{}</td><td>{have}</td><td>{missing}</td></tr>"#,
+            escape(system),
+            system_summary.pass_count,
+            system_summary.fail_count
+        )?;
+    }
+
+    writeln!(w, "</tbody>")?;
+    writeln!(
+        w,
+        r#"<tfoot><tr><td>Total</td><td class="pass">{}</td><td class="fail">{}</td><td></td><td></td></tr></tfoot>"#,
+        summary.total_passes, summary.total_fails
+    )?;
+    writeln!(w, "</table>")?;
+
+    let duration = summary.duration().unwrap_or_default().as_secs_f64();
+    writeln!(
+        w,
+        "<p>Scanned {} items in {duration:.3}s</p>",
+        summary.total_passes + summary.total_fails
+    )
+}
+
+fn write_report<W: Write>(w: &mut W, report: &Report) -> io::Result<()> {
+    writeln!(w, r#"<details class="file">"#)?;
+    writeln!(
+        w,
+        r#"<summary>{} <span class="fail">({})</span></summary>"#,
+        escape(&report.path),
+        report.diagnostics.len()
+    )?;
+    writeln!(w, "<ul>")?;
+
+    for diagnostic in &report.diagnostics {
+        let lint = diagnostic.lint.as_deref().unwrap_or_default();
+
+        write!(
+            w,
+            r#"<li data-lint="{}" data-severity="{SEVERITY}"><span class="lint">{}</span>{}"#,
+            escape(lint),
+            escape(lint),
+            escape(&diagnostic.message)
+        )?;
+
+        for hint in diagnostic.hints.as_deref().unwrap_or_default() {
+            write!(w, r#"<span class="hint">{}</span>"#, escape(hint))?;
+        }
+
+        writeln!(w, "</li>")?;
+    }
+
+    writeln!(w, "</ul>")?;
+    writeln!(w, "</details>")
+}
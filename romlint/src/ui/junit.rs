@@ -0,0 +1,135 @@
+use super::{escape, Message, Reporter, Summary};
+use crate::error::{IoErr, Result};
+use crate::linter::Diagnostic;
+use snafu::prelude::*;
+use std::collections::{BTreeMap, HashMap};
+use std::io::{self, Write};
+
+struct TestCase {
+    path: String,
+    failures: Vec<Diagnostic>,
+}
+
+/// Writes a JUnit XML document once linting has finished. Each system is a test suite, each file
+/// a test case, and each diagnostic a failure of that test case.
+pub struct JunitReporter {
+    suites: BTreeMap<String, Vec<TestCase>>,
+    /// Where each path's test case is, since collection lints can report a file again after it
+    /// was checked on its own
+    cases: HashMap<String, (String, usize)>,
+}
+
+impl JunitReporter {
+    pub fn new() -> Self {
+        Self {
+            suites: BTreeMap::new(),
+            cases: HashMap::new(),
+        }
+    }
+
+    fn write<W: Write>(&self, w: &mut W, summary: &Summary) -> io::Result<()> {
+        let tests = self.suites.values().map(Vec::len).sum::<usize>();
+        let failures = self
+            .suites
+            .values()
+            .map(|cases| failure_count(cases))
+            .sum::<usize>();
+        let time = summary.duration().unwrap_or_default().as_secs_f64();
+
+        writeln!(w, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+        writeln!(
+            w,
+            r#"<testsuites name="romlint" tests="{tests}" failures="{failures}" time="{time:.3}">"#
+        )?;
+
+        for (system, cases) in &self.suites {
+            writeln!(
+                w,
+                r#"  <testsuite name="{}" tests="{}" failures="{}">"#,
+                escape(system),
+                cases.len(),
+                failure_count(cases)
+            )?;
+
+            for case in cases {
+                write_case(w, system, case)?;
+            }
+
+            writeln!(w, "  </testsuite>")?;
+        }
+
+        writeln!(w, "</testsuites>")?;
+        w.flush()
+    }
+}
+
+impl Reporter for JunitReporter {
+    fn on_tick(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    fn on_message(&mut self, message: Message) -> Result<()> {
+        match message {
+            Message::Report(report) => {
+                if let Some((system, i)) = self.cases.get(&report.path) {
+                    self.suites.get_mut(system).unwrap()[*i]
+                        .failures
+                        .extend(report.diagnostics);
+                    return Ok(());
+                }
+
+                let system = report.system.unwrap_or_else(|| "unknown".to_owned());
+                let cases = self.suites.entry(system.clone()).or_default();
+                self.cases
+                    .insert(report.path.clone(), (system, cases.len()));
+                cases.push(TestCase {
+                    path: report.path,
+                    failures: report.diagnostics,
+                });
+            }
+            Message::Finished(summary) => {
+                self.write(&mut io::stdout().lock(), &summary)
+                    .context(IoErr { path: "stdout" })?;
+            }
+            _ => {}
+        }
+
+        Ok(())
+    }
+}
+
+fn write_case<W: Write>(w: &mut W, system: &str, case: &TestCase) -> io::Result<()> {
+    let name = escape(&case.path);
+    let classname = escape(system);
+
+    if case.failures.is_empty() {
+        return writeln!(
+            w,
+            r#"    <testcase name="{name}" classname="{classname}"/>"#
+        );
+    }
+
+    writeln!(w, r#"    <testcase name="{name}" classname="{classname}">"#)?;
+
+    for failure in &case.failures {
+        let lint = failure.lint.as_deref().unwrap_or("romlint");
+        let hints = failure.hints.as_deref().unwrap_or_default().join("\n");
+
+        writeln!(
+            w,
+            r#"      <failure type="{}" message="{}">{}</failure>"#,
+            escape(lint),
+            escape(&failure.message),
+            escape(&hints)
+        )?;
+    }
+
+    writeln!(w, "    </testcase>")
+}
+
+fn failure_count(cases: &[TestCase]) -> usize {
+    cases
+        .iter()
+        .filter(|case| !case.failures.is_empty())
+        .count()
+}
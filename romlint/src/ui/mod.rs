@@ -1,17 +1,36 @@
+mod html;
+mod junit;
+mod ndjson;
+mod sarif;
+
 use crate::ansi::{clear_line, move_to_line_start, move_up_lines, print_status};
 use crate::error::{IoErr, Result};
 use crate::linter::Diagnostic;
 use nu_ansi_term::Color::{Blue, Green, Red};
 use serde::Serialize;
 use snafu::prelude::*;
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap};
 use std::time::Instant;
 use std::{sync::mpsc::Receiver, time::Duration};
 
+pub use html::HtmlReporter;
+pub use junit::JunitReporter;
+pub use ndjson::NdjsonReporter;
+pub use sarif::SarifReporter;
+
 #[derive(Default, Serialize)]
 pub struct SystemSummary {
     pass_count: usize,
     fail_count: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    collection: Option<Collection>,
+}
+
+/// How many of the games in a system's DATs are in the collection
+#[derive(Clone, Copy, Serialize)]
+pub struct Collection {
+    pub have: usize,
+    pub missing: usize,
 }
 
 #[derive(Serialize)]
@@ -46,15 +65,35 @@ impl Summary {
         self.systems.entry(system.into()).or_default().fail_count += 1;
     }
 
+    /// Count a file which already passed as a failure instead
+    pub fn fail_passed<S: Into<String>>(&mut self, system: S) {
+        let summary = self.systems.entry(system.into()).or_default();
+        summary.pass_count -= 1;
+        summary.fail_count += 1;
+        self.total_passes -= 1;
+        self.total_fails += 1;
+    }
+
+    pub fn set_collection<S: Into<String>>(&mut self, system: S, collection: Collection) {
+        self.systems.entry(system.into()).or_default().collection = Some(collection);
+    }
+
     pub fn mark_ended(&mut self) {
         self.end_time = Some(Instant::now());
     }
+
+    /// How long the run took, once it has ended
+    pub fn duration(&self) -> Option<Duration> {
+        self.end_time
+            .map(|end_time| end_time.duration_since(self.start_time))
+    }
 }
 
 #[derive(Debug)]
 pub struct Report {
     pub diagnostics: Vec<Diagnostic>,
     pub path: String,
+    pub system: Option<String>,
 }
 
 impl Report {
@@ -66,7 +105,7 @@ impl Report {
 #[derive(Serialize)]
 struct JsonReport<'a> {
     diagnostics: &'a HashMap<String, Vec<Diagnostic>>,
-    passes: &'a Vec<String>,
+    passes: &'a BTreeSet<String>,
     summary: &'a Summary,
 }
 
@@ -78,6 +117,11 @@ pub enum Message {
     Report(Report),
 }
 
+/// Drop messages, for commands which load databases without showing any progress
+pub fn nop(_message: Message) -> Result<()> {
+    Ok(())
+}
+
 pub trait Reporter {
     fn on_tick(&mut self) -> Result<()>;
     fn on_message(&mut self, message: Message) -> Result<()>;
@@ -156,13 +200,13 @@ impl Reporter for AnsiReporter {
 
 pub struct JsonReporter {
     diagnostics: HashMap<String, Vec<Diagnostic>>,
-    passes: Vec<String>,
+    passes: BTreeSet<String>,
 }
 
 impl JsonReporter {
     pub fn new() -> Self {
         let diagnostics = HashMap::new();
-        let passes = Vec::new();
+        let passes = BTreeSet::new();
 
         Self {
             diagnostics,
@@ -180,9 +224,14 @@ impl Reporter for JsonReporter {
         match message {
             Message::Report(report) => {
                 if report.diagnostics.is_empty() {
-                    self.passes.push(report.path);
+                    self.passes.insert(report.path);
                 } else {
-                    self.diagnostics.insert(report.path, report.diagnostics);
+                    // Collection lints can report a file again after it was checked on its own
+                    self.passes.remove(&report.path);
+                    self.diagnostics
+                        .entry(report.path)
+                        .or_default()
+                        .extend(report.diagnostics);
                 }
             }
             Message::Finished(summary) => {
@@ -244,8 +293,7 @@ fn print_loading_status(items: &[(String, bool)]) {
 }
 
 fn print_summary(summary: &Summary) {
-    let duration = summary.end_time.unwrap().duration_since(summary.start_time);
-    let duration = format_duration(&duration);
+    let duration = format_duration(&summary.duration().unwrap());
 
     println!("          Passed   Failed");
     for (system, summary) in &summary.systems {
@@ -308,3 +356,43 @@ fn print_report(report: &Report, show_passes: bool) {
         }
     }
 }
+
+/// Escape text for use in XML or HTML, in either content or attribute values. XML 1.0 doesn't
+/// allow control characters other than tabs and line breaks, even escaped, so those are replaced.
+fn escape(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+
+    for c in s.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            '\t' | '\n' | '\r' => escaped.push(c),
+            c if c < ' ' => escaped.push(char::REPLACEMENT_CHARACTER),
+            c => escaped.push(c),
+        }
+    }
+
+    escaped
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn escapes_markup() {
+        assert_eq!(
+            escape(r#"<a href="x">'&'</a>"#),
+            "&lt;a href=&quot;x&quot;&gt;&apos;&amp;&apos;&lt;/a&gt;"
+        );
+    }
+
+    #[test]
+    fn replaces_control_characters() {
+        assert_eq!(escape("bad\u{0}dump\u{1b}"), "bad\u{fffd}dump\u{fffd}");
+        assert_eq!(escape("a\tb\r\nc"), "a\tb\r\nc");
+    }
+}
@@ -0,0 +1,98 @@
+//! Newline-delimited JSON output. Every line is a single JSON object with a `type` field, and
+//! lines are written as soon as they are available, so consumers can process results while a
+//! scan is still running.
+//!
+//! A `report` line is written for every file which was linted. Collection lints run once every
+//! file has been linted, so a file can have a second `report` line with their diagnostics:
+//!
+//! ```json
+//! {"type":"report","path":"gb/Tetris (World).gb","system":"gb","passed":false,
+//!  "diagnostics":[{"lint":"uncompressed_file","message":"File is not archived","hints":[]}]}
+//! ```
+//!
+//! Once linting has finished, a single `summary` line is written last. Systems with a loaded DAT
+//! also have a `collection` field, counting the games which are present and missing:
+//!
+//! ```json
+//! {"type":"summary","systems":{"gb":{"pass_count":1,"fail_count":1,
+//!  "collection":{"have":2,"missing":2}}},"total_passes":1,"total_fails":1,"duration_ms":12}
+//! ```
+//!
+//! New fields may be added to either kind of line, but existing fields won't be removed or
+//! change meaning.
+
+use super::{Message, Reporter, Summary};
+use crate::error::{IoErr, Result};
+use crate::linter::Diagnostic;
+use serde::Serialize;
+use snafu::prelude::*;
+use std::io::{self, Write};
+
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+enum Line<'a> {
+    Report {
+        path: &'a str,
+        system: Option<&'a str>,
+        passed: bool,
+        diagnostics: Vec<LineDiagnostic<'a>>,
+    },
+    Summary {
+        #[serde(flatten)]
+        summary: &'a Summary,
+        duration_ms: u128,
+    },
+}
+
+#[derive(Serialize)]
+struct LineDiagnostic<'a> {
+    lint: Option<&'a str>,
+    message: &'a str,
+    hints: &'a [String],
+}
+
+impl<'a> From<&'a Diagnostic> for LineDiagnostic<'a> {
+    fn from(diagnostic: &'a Diagnostic) -> Self {
+        Self {
+            lint: diagnostic.lint.as_deref(),
+            message: &diagnostic.message,
+            hints: diagnostic.hints.as_deref().unwrap_or_default(),
+        }
+    }
+}
+
+/// Writes one line of JSON per linted file as results arrive, followed by a summary line
+pub struct NdjsonReporter;
+
+impl NdjsonReporter {
+    fn write_line(line: &Line) -> Result<()> {
+        let mut stdout = io::stdout().lock();
+        let serialized = serde_json::to_string(line).unwrap();
+
+        writeln!(stdout, "{serialized}")
+            .and_then(|_| stdout.flush())
+            .context(IoErr { path: "stdout" })
+    }
+}
+
+impl Reporter for NdjsonReporter {
+    fn on_tick(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    fn on_message(&mut self, message: Message) -> Result<()> {
+        match message {
+            Message::Report(report) => Self::write_line(&Line::Report {
+                path: &report.path,
+                system: report.system.as_deref(),
+                passed: report.ok(),
+                diagnostics: report.diagnostics.iter().map(Into::into).collect(),
+            }),
+            Message::Finished(summary) => Self::write_line(&Line::Summary {
+                summary: &summary,
+                duration_ms: summary.duration().unwrap_or_default().as_millis(),
+            }),
+            _ => Ok(()),
+        }
+    }
+}
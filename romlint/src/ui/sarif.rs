@@ -0,0 +1,206 @@
+use super::{Message, Reporter};
+use crate::error::{IoErr, Result};
+use crate::scripts::Script;
+use serde::Serialize;
+use snafu::prelude::*;
+use std::collections::HashMap;
+use std::io::{self, Write};
+
+const SCHEMA: &str = "https://json.schemastore.org/sarif-2.1.0.json";
+
+/// A lint, as SARIF describes it
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Rule {
+    id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    short_description: Option<Text>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    help: Option<Text>,
+}
+
+impl From<&Script> for Rule {
+    fn from(script: &Script) -> Self {
+        Self {
+            id: script.name().to_owned(),
+            short_description: script.description().map(Text::new),
+            help: script.help().map(Text::new),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct Text {
+    text: String,
+}
+
+impl Text {
+    fn new<S: Into<String>>(text: S) -> Self {
+        Self { text: text.into() }
+    }
+}
+
+#[derive(Serialize)]
+struct Log<'a> {
+    #[serde(rename = "$schema")]
+    schema: &'static str,
+    version: &'static str,
+    runs: [Run<'a>; 1],
+}
+
+#[derive(Serialize)]
+struct Run<'a> {
+    tool: Tool<'a>,
+    results: &'a [SarifResult],
+}
+
+#[derive(Serialize)]
+struct Tool<'a> {
+    driver: Driver<'a>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Driver<'a> {
+    name: &'static str,
+    version: &'static str,
+    rules: &'a [Rule],
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SarifResult {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    rule_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    rule_index: Option<usize>,
+    level: &'static str,
+    message: Text,
+    locations: [Location; 1],
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Location {
+    physical_location: PhysicalLocation,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct PhysicalLocation {
+    artifact_location: ArtifactLocation,
+}
+
+#[derive(Serialize)]
+struct ArtifactLocation {
+    uri: String,
+}
+
+/// Writes a SARIF 2.1.0 log once linting has finished. Each lint script is a rule, and each
+/// diagnostic a result located at the offending file.
+pub struct SarifReporter {
+    rules: Vec<Rule>,
+    /// Each rule's position in `rules`, by id
+    rule_indexes: HashMap<String, usize>,
+    results: Vec<SarifResult>,
+}
+
+impl SarifReporter {
+    pub fn new(mut rules: Vec<Rule>) -> Self {
+        rules.sort_unstable_by(|a, b| a.id.cmp(&b.id));
+        let rule_indexes = rules
+            .iter()
+            .enumerate()
+            .map(|(index, rule)| (rule.id.clone(), index))
+            .collect();
+
+        Self {
+            rules,
+            rule_indexes,
+            results: Vec::new(),
+        }
+    }
+}
+
+impl Reporter for SarifReporter {
+    fn on_tick(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    fn on_message(&mut self, message: Message) -> Result<()> {
+        match message {
+            Message::Report(report) => {
+                for diagnostic in report.diagnostics {
+                    let rule_index = diagnostic
+                        .lint
+                        .as_ref()
+                        .and_then(|lint| self.rule_indexes.get(lint).copied());
+                    let mut message = diagnostic.message;
+
+                    for hint in diagnostic.hints.unwrap_or_default() {
+                        message.push('\n');
+                        message.push_str(&hint);
+                    }
+
+                    self.results.push(SarifResult {
+                        rule_id: diagnostic.lint,
+                        rule_index,
+                        level: "error",
+                        message: Text::new(message),
+                        locations: [Location {
+                            physical_location: PhysicalLocation {
+                                artifact_location: ArtifactLocation {
+                                    uri: encode_uri(&report.path),
+                                },
+                            },
+                        }],
+                    });
+                }
+            }
+            Message::Finished(_) => {
+                let log = Log {
+                    schema: SCHEMA,
+                    version: "2.1.0",
+                    runs: [Run {
+                        tool: Tool {
+                            driver: Driver {
+                                name: env!("CARGO_PKG_NAME"),
+                                version: env!("CARGO_PKG_VERSION"),
+                                rules: &self.rules,
+                            },
+                        },
+                        results: &self.results,
+                    }],
+                };
+
+                let serialized = serde_json::to_string(&log).unwrap();
+                let mut stdout = io::stdout().lock();
+                writeln!(stdout, "{serialized}")
+                    .and_then(|_| stdout.flush())
+                    .context(IoErr { path: "stdout" })?;
+            }
+            _ => {}
+        }
+
+        Ok(())
+    }
+}
+
+/// Characters which may appear unencoded in the path of a URI, other than letters and digits
+const URI_SAFE: &[u8] = b"-._~/!$&'()*+,;=:@";
+
+/// Turn a relative path into a relative URI reference. ROM names are full of spaces, which need
+/// to be percent-encoded.
+fn encode_uri(path: &str) -> String {
+    let mut uri = String::with_capacity(path.len());
+
+    for byte in path.bytes() {
+        if byte.is_ascii_alphanumeric() || URI_SAFE.contains(&byte) {
+            uri.push(byte as char);
+        } else {
+            uri.push_str(&format!("%{byte:02X}"));
+        }
+    }
+
+    uri
+}